@@ -1,21 +1,59 @@
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind, KeyCode, KeyModifiers};
+use crossterm::event::{
+    poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
 use std::{
-    env, io::Error, panic::{set_hook, take_hook}
+    collections::VecDeque,
+    io::{stdin, Error, Read},
+    mem,
+    panic::{set_hook, take_hook},
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, TryRecvError},
+    time::{Duration, Instant},
 };
 mod annotatedstring;
+mod arguments;
+mod batch;
 mod command;
+mod gototarget;
 mod uicomponents;
 mod documentstatus;
+mod fuzzy;
+mod gitignore;
+mod keyboardmacro;
 mod line;
+mod pathcompletion;
 mod terminal;
 mod prelude;
+mod projectfiles;
+mod projectsearch;
+mod prompthistory;
+mod settings;
+mod shellfilter;
+mod statusformat;
+#[cfg(test)]
+mod tests;
 use prelude::*;
 
 use annotatedstring::{AnnotatedString, AnnotationType};
-use uicomponents::{CommandBar,MessageBar,View, StatusBar, UIComponent};
+pub use arguments::{Arguments, Invocation, USAGE};
+pub use batch::run_batch;
+use arguments::{FileArgument, OpenFile, StartPosition};
+use uicomponents::{
+    entry_name, CommandBar, FileFinder, FileOperation, MessageBar, View, StatusBar, UIComponent,
+};
 use documentstatus::DocumentStatus;
-use line::Line;
-use terminal::Terminal;
+use gototarget::GotoTarget;
+use keyboardmacro::{MacroRecording, MacroRegisters, MacroStep};
+use line::{Line, WordRules};
+use pathcompletion::expand_dir;
+use projectfiles::list_project_files;
+use projectsearch::{search_project, ProjectMatch, ProjectSearchEvent};
+use prompthistory::PromptHistory;
+use settings::Settings;
+use shellfilter::{run_filter, FilterOutput};
+use statusformat::StatusFormat;
+use terminal::{ColorSupport, Terminal, Theme};
 use self::command::Bindings;
 
 use stack_editor_macros::insert_into_map;
@@ -24,15 +62,62 @@ use stack_editor_macros::insert_into_map;
 enum PromptType {
     Save,
     Find,
+    GotoLine,
+    Open,
+    FindFile,
+    Grep,
+    CreateFile,
+    CreateDirectory,
+    Rename,
+    Delete,
+    RecordMacro,
+    PlayMacro,
+    Filter,
     #[default]
     None,
 }
 
+impl PromptType {
+    /// The name under which values entered into this prompt are remembered.
+    const fn history_kind(&self) -> Option<&'static str> {
+        match self {
+            Self::Save => Some("save"),
+            Self::Find => Some("find"),
+            Self::GotoLine => Some("goto"),
+            Self::Open => Some("open"),
+            Self::FindFile => Some("find_file"),
+            Self::Grep => Some("grep"),
+            Self::PlayMacro => Some("play_macro"),
+            Self::Filter => Some("filter"),
+            Self::CreateFile
+            | Self::CreateDirectory
+            | Self::Rename
+            | Self::Delete
+            | Self::RecordMacro
+            | Self::None => None,
+        }
+    }
+
+    const fn completes_paths(&self) -> bool {
+        matches!(self, Self::Save | Self::Open)
+    }
+
+    /// Whether the prompt changes the directory shown in the directory browser.
+    const fn is_file_operation(&self) -> bool {
+        matches!(
+            self,
+            Self::CreateFile | Self::CreateDirectory | Self::Rename | Self::Delete
+        )
+    }
+}
+
 
 #[derive(Default)]
 pub struct Editor {
     should_quit: bool,
     view: View,
+    /// The views of the other files given on the command line, in the order they are cycled through.
+    other_views: VecDeque<View>,
     bindings: Bindings,
     status_bar: StatusBar,
     message_bar: MessageBar,
@@ -40,25 +125,78 @@ pub struct Editor {
     title: String,
     quit_times: u8,
     command_bar: CommandBar,
+    file_finder: FileFinder,
     prompt_type: PromptType,
+    settings: Settings,
+    last_click: Option<(Instant, Position)>,
+    /// The results of the running project search, if there is one
+    project_search: Option<Receiver<ProjectSearchEvent>>,
+    /// The directory the file finder and the project search look in
+    project_root: PathBuf,
+    /// The files of the project for the file finder, while they are being listed
+    project_files: Option<Receiver<Vec<String>>>,
+    macros: MacroRegisters,
+    /// The macro being recorded, if there is one
+    macro_recording: Option<MacroRecording>,
 }
 
 impl Editor {
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(arguments: &Arguments) -> Result<Self, Error> {
         let current_hook = take_hook();
         set_hook(Box::new(move |panic_info| {
             let _ = Terminal::terminate();
             current_hook(panic_info);
         }));
+        let (settings, settings_errors) = Settings::load();
+        let (theme, theme_errors) = Theme::load(&settings.theme);
+        Terminal::set_theme(theme);
+        Terminal::set_color_support(settings.color_support.unwrap_or_else(ColorSupport::detect));
+        // Standard input has to be consumed before the terminal switches to raw mode
+        let stdin_contents = arguments
+            .files
+            .iter()
+            .any(|open_file| open_file.file == FileArgument::Stdin)
+            .then(|| {
+                let mut contents = String::new();
+                stdin().read_to_string(&mut contents).map(|_| contents)
+            });
         Terminal::initialize()?;
+        if settings.mouse_capture {
+            Terminal::enable_mouse_capture()?;
+        }
 
         let mut editor = Self::default();
+        editor.view.set_settings(settings.clone());
+        editor
+            .status_bar
+            .set_format(settings.status_left.clone(), settings.status_right.clone());
+        editor.settings = settings;
+        editor.project_root = PathBuf::from(".");
+        editor.command_bar.set_history(PromptHistory::load());
+        editor.macros = MacroRegisters::load();
         let size = Terminal::size().unwrap_or_default();
         
         insert_into_map!(&mut editor.bindings, {
             (KeyCode::Char('s'), KeyModifiers::CONTROL) => "save",
             (KeyCode::Char('q'), KeyModifiers::CONTROL) => "quit",
             (KeyCode::Char('f'), KeyModifiers::CONTROL) => "find",
+            (KeyCode::Char('o'), KeyModifiers::CONTROL) => "open",
+            (KeyCode::Char('t'), KeyModifiers::CONTROL) => "find_file",
+            (KeyCode::Char('f'), KeyModifiers::ALT) => "grep",
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => "goto_line",
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => "toggle_read_only",
+            (KeyCode::Char('b'), KeyModifiers::CONTROL) => "matching_bracket",
+            (KeyCode::Char('w'), KeyModifiers::ALT) => "toggle_whitespace",
+            (KeyCode::Char('t'), KeyModifiers::ALT) => "strip_trailing_whitespace",
+            (KeyCode::Char('m'), KeyModifiers::ALT) => "message_history",
+            (KeyCode::Char('r'), KeyModifiers::ALT) => "record_macro",
+            (KeyCode::Char('s'), KeyModifiers::ALT) => "stop_macro",
+            (KeyCode::Char('p'), KeyModifiers::ALT) => "play_macro",
+            (KeyCode::Char('|'), KeyModifiers::ALT) => "filter",
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => "kill_to_start",
+            (KeyCode::Char('k'), KeyModifiers::CONTROL) => "kill_to_end",
+            (KeyCode::Char('p'), KeyModifiers::CONTROL) => "history_previous",
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) => "history_next",
             (KeyCode::Up, KeyModifiers::NONE) => "move_up",
             (KeyCode::Down, KeyModifiers::NONE) => "move_down",
             (KeyCode::Left, KeyModifiers::NONE) => "move_left",
@@ -72,33 +210,456 @@ impl Editor {
             (KeyCode::Enter, KeyModifiers::NONE) => "insert_newline",
             (KeyCode::Backspace, KeyModifiers::NONE) => "delete_backward",
             (KeyCode::Delete, KeyModifiers::NONE) => "delete",
+            (KeyCode::Left, KeyModifiers::CONTROL) => "move_word_left",
+            (KeyCode::Right, KeyModifiers::CONTROL) => "move_word_right",
+            (KeyCode::Backspace, KeyModifiers::CONTROL) => "delete_word_backward",
+            (KeyCode::Backspace, KeyModifiers::ALT) => "delete_word_backward",
+            (KeyCode::Delete, KeyModifiers::CONTROL) => "delete_word",
+            (KeyCode::PageDown, KeyModifiers::CONTROL) => "next_file",
+            (KeyCode::PageUp, KeyModifiers::CONTROL) => "previous_file",
         });
 
         editor.resize(size);
-        editor
-            .message_bar
-            .update_message("HELP: Ctrl-F = find | Ctrl-S = save | Ctrl-Q = quit");
-
-        let args: Vec<String> = env::args().collect();
-        if let Some(file_name) = args.get(1) {
-            debug_assert!(!file_name.is_empty());
-            if editor.view.load(file_name).is_err() {
-                editor
-                    .message_bar
-                    .update_message(&format!("ERR: Could not open file: {file_name}"));
-            }
+        editor.update_message(
+            Severity::Info,
+            "HELP: Ctrl-O = open | Ctrl-T = find file | Ctrl-F = find | Ctrl-G = go to line | Ctrl-S = save | Ctrl-Q = quit",
+        );
+        if let Some(err) = settings_errors.first() {
+            editor.update_message(Severity::Error, &format!("Invalid setting: {err}"));
+        }
+        if let Some(err) = theme_errors.first() {
+            editor.update_message(Severity::Error, &format!("Invalid theme: {err}"));
         }
+        editor.open_files(arguments, stdin_contents);
 
         editor.refresh_status();
         Ok(editor)
     }
 
+    /// Opens the files given on the command line, each in its own view.
+    fn open_files(&mut self, arguments: &Arguments, stdin_contents: Option<Result<String, Error>>) {
+        let mut stdin_contents = stdin_contents;
+        let mut views = Vec::new();
+        // Reported once all files are open, as each message would replace the one before
+        let mut problems: Vec<(Severity, String)> = Vec::new();
+        for OpenFile {
+            file,
+            start_position,
+        } in &arguments.files
+        {
+            let mut view = self.new_view();
+            match file {
+                FileArgument::Path(file_name) if Path::new(file_name).is_dir() => {
+                    if let Err(err) = view.open_directory(Path::new(file_name)) {
+                        problems.push((Severity::Error, format!("Could not list {file_name}: {err}")));
+                    } else {
+                        self.show_browser_help();
+                    }
+                }
+                FileArgument::Path(file_name) => {
+                    debug_assert!(!file_name.is_empty());
+                    if view.load(file_name).is_err() {
+                        problems.push((Severity::Error, format!("Could not open file: {file_name}")));
+                    } else if view.is_read_only() && !arguments.read_only {
+                        problems.push((
+                            Severity::Warning,
+                            format!("{file_name} is not writable, opened it read-only."),
+                        ));
+                    }
+                }
+                FileArgument::Stdin => match stdin_contents.take() {
+                    Some(Ok(contents)) => view.load_contents(&contents),
+                    Some(Err(err)) => {
+                        problems.push((Severity::Error, format!("Could not read standard input: {err}")));
+                    }
+                    None => {}
+                },
+            }
+            if arguments.read_only {
+                view.set_read_only(true);
+            }
+            match start_position {
+                Some(StartPosition::Location { line, col }) => {
+                    if let Err(err) = view.goto(GotoTarget::Line {
+                        line: *line,
+                        col: *col,
+                    }) {
+                        problems.push((Severity::Error, err));
+                    }
+                }
+                Some(StartPosition::Pattern(pattern)) if !view.find_first(pattern) => {
+                    problems.push((Severity::Error, format!("Pattern not found: {pattern}")));
+                }
+                Some(StartPosition::Pattern(_)) | None => {}
+            }
+            views.push(view);
+        }
+
+        for (severity, message) in &problems {
+            self.update_message(*severity, message);
+        }
+        if problems.len() > 1 {
+            let severity = if problems.iter().any(|(severity, _)| *severity == Severity::Error) {
+                Severity::Error
+            } else {
+                Severity::Warning
+            };
+            self.update_message(
+                severity,
+                &format!("{} problems opening the files. Alt-M shows them all.", problems.len()),
+            );
+        }
+
+        let mut views = views.into_iter();
+        if let Some(first) = views.next() {
+            self.view = first;
+        }
+        self.other_views = views.collect();
+    }
+
+    fn view_size(&self) -> Size {
+        Size {
+            height: self.terminal_size.height.saturating_sub(2),
+            width: self.terminal_size.width,
+        }
+    }
+
+    fn new_view(&self) -> View {
+        let mut view = View::default();
+        view.set_settings(self.settings.clone());
+        view.resize(self.view_size());
+        view
+    }
+
+    /// Makes the next file given on the command line the current one, or the previous one if `forward` is false.
+    fn switch_file(&mut self, forward: bool) {
+        let next = if forward {
+            self.other_views.pop_front()
+        } else {
+            self.other_views.pop_back()
+        };
+        let Some(next) = next else {
+            self.update_message(Severity::Info, "There are no other files to switch to.");
+            return;
+        };
+        let previous = mem::replace(&mut self.view, next);
+        if forward {
+            self.other_views.push_back(previous);
+        } else {
+            self.other_views.push_front(previous);
+        }
+        self.view.resize(self.view_size());
+        self.refresh_status();
+    }
+
+    /// Opens the messages shown so far in a read-only view, replacing an earlier one.
+    fn show_message_history(&mut self) {
+        self.other_views.retain(|view| !view.is_disposable());
+        let mut history = self.new_view();
+        history.load_scratch("[Messages]", &self.message_bar.history());
+        // Show the most recent messages first
+        let _ = history.goto(GotoTarget::Percent(100));
+        let previous = mem::replace(&mut self.view, history);
+        if !previous.is_disposable() {
+            self.keep_scratch_origin(previous);
+        }
+        self.refresh_status();
+    }
+
+    /// Keeps `view` with the other files as the one to return to once the scratch view shown in
+    /// front of it is closed. Switching files can reorder the other files, so they can't tell.
+    fn keep_scratch_origin(&mut self, mut view: View) {
+        for other in &mut self.other_views {
+            other.set_scratch_origin(false);
+        }
+        view.set_scratch_origin(true);
+        self.other_views.push_back(view);
+    }
+
+    /// Closes the message history, a directory browser or search results, returning to the view
+    /// which was shown before it.
+    fn close_scratch_view(&mut self) {
+        let origin = self
+            .other_views
+            .iter()
+            .position(View::is_scratch_origin)
+            .or_else(|| self.other_views.len().checked_sub(1));
+        if let Some(previous) = origin.and_then(|idx| self.other_views.remove(idx)) {
+            self.view = previous;
+            self.view.resize(self.view_size());
+            self.view.set_needs_redraw(true);
+            self.refresh_status();
+        } else {
+            self.handle_quit();
+        }
+    }
+
+    /// Makes `view` the current one. The view shown so far is kept with the other files, unless it
+    /// is disposable or an untouched empty buffer.
+    fn replace_view(&mut self, view: View) {
+        let previous = mem::replace(&mut self.view, view);
+        let status = previous.get_status();
+        let is_untouched = !previous.is_file_loaded() && !status.is_modified && status.total_lines == 0;
+        if !previous.is_disposable() && !is_untouched {
+            if self.view.is_scratch() {
+                self.keep_scratch_origin(previous);
+            } else {
+                self.other_views.push_back(previous);
+            }
+        }
+        self.view.resize(self.view_size());
+        self.view.set_needs_redraw(true);
+        self.refresh_status();
+    }
+
+    /// Opens `path` as entered by the user: a directory in the directory browser, a file which is
+    /// already open by switching to it, and a file which doesn't exist yet as a new, empty buffer.
+    fn open_path(&mut self, path: &str) {
+        let Some(path) = expand_dir(path) else {
+            self.update_message(Severity::Error, "Could not find the home directory.");
+            return;
+        };
+        if path.is_dir() {
+            self.open_directory(&path);
+            return;
+        }
+        let is_same_file = |view: &View| {
+            view.file_path()
+                .and_then(|open| open.canonicalize().ok())
+                .is_some_and(|open| path.canonicalize().is_ok_and(|path| path == open))
+        };
+        if is_same_file(&self.view) {
+            return;
+        }
+        if let Some(idx) = self.other_views.iter().position(is_same_file) {
+            if let Some(view) = self.other_views.remove(idx) {
+                self.replace_view(view);
+            }
+            return;
+        }
+
+        let file_name = path.to_string_lossy();
+        let mut view = self.new_view();
+        if path.exists() {
+            if let Err(err) = view.load(&file_name) {
+                self.update_message(Severity::Error, &format!("Could not open {file_name}: {err}"));
+                return;
+            }
+            if view.is_read_only() {
+                self.update_message(
+                    Severity::Warning,
+                    &format!("{file_name} is not writable, opened it read-only."),
+                );
+            }
+        } else {
+            view.new_file(&file_name);
+            self.update_message(Severity::Info, &format!("New file: {file_name}"));
+        }
+        self.replace_view(view);
+    }
+
+    /// Lists `dir` in the directory browser, reusing the current view if it is one already.
+    fn open_directory(&mut self, dir: &Path) {
+        let result = if self.view.directory().is_some() {
+            self.view.open_directory(dir)
+        } else {
+            let mut view = self.new_view();
+            view.open_directory(dir).map(|()| self.replace_view(view))
+        };
+        match result {
+            Ok(()) => self.show_browser_help(),
+            Err(err) => self.update_message(
+                Severity::Error,
+                &format!("Could not list {}: {err}", dir.display()),
+            ),
+        }
+    }
+
+    /// Starts searching the files below the working directory for `query`, listing the results in
+    /// a new view which replaces the results of an earlier search.
+    fn start_project_search(&mut self, query: &str) {
+        self.other_views.retain(|view| !view.is_project_search());
+        let mut results = self.new_view();
+        results.start_project_search(&format!("[Search: {query}]"));
+        if self.view.is_project_search() {
+            self.view = results;
+            self.view.set_needs_redraw(true);
+            self.refresh_status();
+        } else {
+            self.replace_view(results);
+        }
+        self.project_search = Some(search_project(&self.project_root, query));
+        self.update_message(Severity::Info, &format!("Searching for {query}..."));
+    }
+
+    /// Hands the project files to the file finder once they are listed, matching them against
+    /// what was typed in the meantime. The list is dropped if the finder was closed.
+    fn receive_project_files(&mut self) {
+        let Some(receiver) = &self.project_files else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(files) => {
+                self.project_files = None;
+                if self.prompt_type == PromptType::FindFile {
+                    self.file_finder.set_files(files);
+                    self.file_finder.set_query(&self.command_bar.value());
+                }
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => self.project_files = None,
+        }
+    }
+
+    /// Adds the results found since the last call to the results view. The search is stopped once
+    /// it is done or its view was closed.
+    fn receive_project_search_results(&mut self) {
+        let Some(receiver) = &self.project_search else {
+            return;
+        };
+        let view = if self.view.is_project_search() {
+            Some(&mut self.view)
+        } else {
+            self.other_views
+                .iter_mut()
+                .find(|view| view.is_project_search())
+        };
+        let Some(view) = view else {
+            self.project_search = None;
+            return;
+        };
+        let files_searched = loop {
+            match receiver.try_recv() {
+                Ok(ProjectSearchEvent::Match(found)) => view.append_project_match(found),
+                Ok(ProjectSearchEvent::Done { files_searched }) => break Some(files_searched),
+                Err(TryRecvError::Empty) => break None,
+                Err(TryRecvError::Disconnected) => break Some(0),
+            }
+        };
+        if let Some(files_searched) = files_searched {
+            let lines = view.get_status().total_lines;
+            self.project_search = None;
+            self.update_message(
+                Severity::Info,
+                &format!("Found {lines} matching lines in {files_searched} files."),
+            );
+        }
+    }
+
+    /// Opens the file of the search result under the caret, at the match.
+    fn open_project_match(&mut self) {
+        let Some((path, location)) = self.view.selected_project_match() else {
+            return;
+        };
+        let path = path.to_string_lossy().into_owned();
+        self.open_path(&path);
+        if self.view.is_project_search() {
+            return;
+        }
+        let target = GotoTarget::Line {
+            line: location.line_idx.saturating_add(1),
+            col: Some(location.grapheme_idx.saturating_add(1)),
+        };
+        if let Err(err) = self.view.goto(target) {
+            self.update_message(Severity::Error, &err);
+        }
+    }
+
+    fn show_browser_help(&mut self) {
+        self.update_message(
+            Severity::Info,
+            "Enter = open | - = parent | n = new file | m = new directory | r = rename | d = delete",
+        );
+    }
+
+    /// Handles the keys of the directory browser. Returns whether `command` was one of them.
+    fn process_browser_command(&mut self, command: &str) -> bool {
+        match command {
+            "insert_newline" => {
+                if let Some(entry) = self.view.selected_entry() {
+                    self.open_path(&entry.to_string_lossy());
+                }
+            }
+            "delete_backward" => self.open_parent_directory(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn process_browser_key(&mut self, c: char) {
+        match c {
+            '-' => self.open_parent_directory(),
+            'n' => self.show_prompt(PromptType::CreateFile),
+            'm' => self.show_prompt(PromptType::CreateDirectory),
+            'r' => self.show_prompt(PromptType::Rename),
+            'd' => self.show_prompt(PromptType::Delete),
+            _ => self.show_browser_help(),
+        }
+    }
+
+    /// Lists the parent of the browsed directory, with the caret on the directory left.
+    fn open_parent_directory(&mut self) {
+        let Some(dir) = self.view.directory().map(Path::to_path_buf) else {
+            return;
+        };
+        if let Some(parent) = dir.parent() {
+            self.open_directory(parent);
+            if let Some(name) = dir.file_name() {
+                self.view.select_entry(&name.to_string_lossy());
+            }
+        }
+    }
+
+    /// Returns the entry under the caret in the directory browser, unless it is the parent directory.
+    fn selected_browser_entry(&self) -> Option<PathBuf> {
+        let dir = self.view.directory()?;
+        self.view
+            .selected_entry()
+            .filter(|entry| !dir.starts_with(entry))
+    }
+
+    /// Carries out a file operation entered into one of the directory browser's prompts.
+    fn run_file_operation(&mut self, prompt_type: &PromptType, value: &str) {
+        let entry = self.selected_browser_entry();
+        if value.is_empty() && *prompt_type != PromptType::Delete {
+            return;
+        }
+        let operation = match (prompt_type, entry) {
+            (PromptType::CreateFile, _) => FileOperation::CreateFile(value.to_string()),
+            (PromptType::CreateDirectory, _) => FileOperation::CreateDirectory(value.to_string()),
+            (PromptType::Rename, Some(entry)) => FileOperation::Rename {
+                entry,
+                to: value.to_string(),
+            },
+            (PromptType::Delete, Some(entry)) if value.eq_ignore_ascii_case("y") => {
+                FileOperation::Delete(entry)
+            }
+            (PromptType::Delete, _) => {
+                self.update_message(Severity::Info, "Nothing was deleted.");
+                return;
+            }
+            _ => return,
+        };
+        match self.view.run_file_operation(&operation) {
+            Ok(message) => self.update_message(Severity::Info, &message),
+            Err(err) => self.update_message(Severity::Error, &err),
+        }
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        self.view.get_status().is_modified
+            || self
+                .other_views
+                .iter()
+                .any(|view| view.get_status().is_modified)
+    }
+
     fn resize(&mut self, size: Size) {
         self.terminal_size = size;
-        self.view.resize(Size {
-            height: size.height.saturating_sub(2),
-            width: size.width,
-        });
+        // Whatever is on screen now is unreliable, so start over from an empty screen.
+        let _ = Terminal::clear_screen();
+        self.view.resize(self.view_size());
+        self.file_finder.resize(self.view_size());
         let bar_size = Size {
             height: 1,
             width: size.width,
@@ -109,7 +670,35 @@ impl Editor {
     }
 
     fn refresh_status(&mut self) {
-        let status = self.view.get_status();
+        let hint = match self.prompt_type {
+            PromptType::FindFile => Some(self.file_finder.summary()),
+            PromptType::None => None,
+            _ => self.command_bar.completion_hint(),
+        };
+        self.status_bar.set_hint(hint);
+        let mut status = self.view.get_status();
+        status.mode = String::from(match self.prompt_type {
+            PromptType::Save => "SAVE AS",
+            PromptType::Find => "SEARCH",
+            PromptType::GotoLine => "GO TO",
+            PromptType::Open => "OPEN",
+            PromptType::FindFile => "FIND FILE",
+            PromptType::Grep => "SEARCH FILES",
+            PromptType::CreateFile => "NEW FILE",
+            PromptType::CreateDirectory => "NEW DIRECTORY",
+            PromptType::Rename => "RENAME",
+            PromptType::Delete => "DELETE",
+            PromptType::RecordMacro => "RECORD MACRO",
+            PromptType::PlayMacro => "PLAY MACRO",
+            PromptType::Filter => "FILTER",
+            PromptType::None if self.view.directory().is_some() => "BROWSE",
+            PromptType::None if self.view.is_project_search() => "RESULTS",
+            PromptType::None if status.is_read_only => "VIEW",
+            PromptType::None => "EDIT",
+        });
+        if self.macro_recording.is_some() {
+            status.mode.push_str(" REC");
+        }
         let title = format!("{} - {NAME}", status.file_name);
         self.status_bar.update_status(status);
 
@@ -124,8 +713,17 @@ impl Editor {
             if self.should_quit {
                 break;
             }
+            if self.project_search.is_some() || self.project_files.is_some() {
+                self.receive_project_search_results();
+                self.receive_project_files();
+                // Keep showing results as they come in until there is input
+                if !matches!(poll(PROJECT_SEARCH_POLL_INTERVAL), Ok(true)) {
+                    self.refresh_status();
+                    continue;
+                }
+            }
             match read() {
-                Ok(event) => self.evaluate_event(event),
+                Ok(event) => self.evaluate_event(&event),
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     {
@@ -138,15 +736,14 @@ impl Editor {
                     }
                 }
             }
-            let status = self.view.get_status();
-            self.status_bar.update_status(status);
+            self.refresh_status();
         }
     }
 
-    fn evaluate_event(&mut self, event: Event) {
-        let should_process = match &event {
+    fn evaluate_event(&mut self, event: &Event) {
+        let should_process = match event {
             Event::Key(KeyEvent { kind, .. }) => kind == &KeyEventKind::Press,
-            Event::Resize(_, _) => true,
+            Event::Resize(_, _) | Event::Mouse(_) | Event::Paste(_) => true,
             _ => false,
         };
 
@@ -156,112 +753,314 @@ impl Editor {
 
         match event {
             Event::Key(key_event) => {
-                let KeyEvent { code, modifiers, .. } = key_event;
-                let command = self.bindings.event_check(key_event).unwrap_or_default();
-
-                match code {
-                    KeyCode::Char(c) => {
-                        if modifiers == KeyModifiers::NONE || modifiers == KeyModifiers::SHIFT {
-                            if self.prompt_type != PromptType::None {
-                                self.command_bar.append_char(c);
-                                self.command_bar.redraw();
-
-                                if self.prompt_type == PromptType::Find {
-                                    let query = self.command_bar.value();
-                                    self.view.search(&query);
-                                }
-                            } else {
-                                self.view.insert_char(c);
-                            }
-                        } else {
-                            self.process_command(command);
-                        }
-                    }
-                    _ => {
-                        self.process_command(command);
+                let KeyEvent { code, modifiers, .. } = *key_event;
+                let step = match code {
+                    KeyCode::Char(c)
+                        if modifiers == KeyModifiers::NONE || modifiers == KeyModifiers::SHIFT =>
+                    {
+                        MacroStep::Insert(c)
                     }
+                    _ => MacroStep::Command(self.bindings.event_check(*key_event).unwrap_or_default()),
+                };
+                self.record_macro_step(&step);
+                self.process_step(&step);
+            }
+            Event::Resize(width_u16, height_u16) => {
+                // clippy::as_conversions: Will run into problems for rare edge case systems where usize < u16
+                #[allow(clippy::as_conversions)]
+                let height = *height_u16 as usize;
+                // clippy::as_conversions: Will run into problems for rare edge case systems where usize < u16
+                #[allow(clippy::as_conversions)]
+                let width = *width_u16 as usize;
+                self.resize(Size { height, width });
+            }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(*mouse_event),
+            Event::Paste(text) => {
+                let step = MacroStep::Paste(text.clone());
+                self.record_macro_step(&step);
+                self.process_step(&step);
+            }
+            _ => {}
+        }
+    }
+
+    /// Processes a typed character, a command or pasted text, whether it was just entered or comes from a macro.
+    fn process_step(&mut self, step: &MacroStep) {
+        match step {
+            MacroStep::Insert(c) => self.process_char(*c),
+            MacroStep::Command(command) => self.process_command(command),
+            MacroStep::Paste(text) => self.handle_paste(text),
+        }
+    }
+
+    fn process_char(&mut self, c: char) {
+        if self.prompt_type == PromptType::None {
+            if self.view.directory().is_some() {
+                self.process_browser_key(c);
+            } else if !self.refuse_if_read_only() {
+                self.view.insert_char(c);
+            }
+        } else {
+            self.command_bar.insert_char(c);
+            self.search_for_prompt_value();
+        }
+    }
+
+    fn record_macro_step(&mut self, step: &MacroStep) {
+        let Some(recording) = &mut self.macro_recording else {
+            return;
+        };
+        if step.is_macro_command() || *step == MacroStep::Command(String::new()) {
+            return;
+        }
+        recording.steps.push(step.clone());
+    }
+
+    /// Starts recording into the register `name`, or the unnamed register if `name` is empty.
+    fn start_macro_recording(&mut self, name: &str) {
+        let name = name.trim();
+        if name.contains(char::is_whitespace) || name.parse::<usize>().is_ok() {
+            self.update_message(
+                Severity::Error,
+                &format!("Invalid macro name `{name}`: use a single word which isn't a number."),
+            );
+            return;
+        }
+        self.macro_recording = Some(MacroRecording {
+            name: name.to_string(),
+            steps: Vec::new(),
+        });
+        let message = if name.is_empty() {
+            String::from("Recording a macro. Alt-S stops recording.")
+        } else {
+            format!("Recording macro {name}. Alt-S stops recording.")
+        };
+        self.update_message(Severity::Info, &message);
+    }
+
+    fn stop_macro_recording(&mut self) {
+        let Some(MacroRecording { name, steps }) = self.macro_recording.take() else {
+            self.update_message(Severity::Info, "Not recording a macro.");
+            return;
+        };
+        if steps.is_empty() {
+            self.update_message(Severity::Info, "Nothing was recorded.");
+            return;
+        }
+        let count = steps.len();
+        self.macros.set(&name, steps);
+        if name.is_empty() {
+            self.update_message(Severity::Info, &format!("Recorded a macro of {count} steps."));
+            return;
+        }
+        if let Err(err) = self.macros.save(&name) {
+            self.update_message(Severity::Warning, &format!("Could not save the macros: {err}"));
+        } else {
+            self.update_message(Severity::Info, &format!("Recorded macro {name} of {count} steps."));
+        }
+    }
+
+    /// Plays a macro as given in the prompt: an optional repeat count followed by the name of a
+    /// register, such as `3 indent`. Without a name the unnamed register is played.
+    fn play_macro(&mut self, value: &str) {
+        let mut words = value.split_whitespace().peekable();
+        let count = match words.peek().map(|word| word.parse::<usize>()) {
+            Some(Ok(count)) => {
+                words.next();
+                count
+            }
+            _ => 1,
+        };
+        let name = words.next().unwrap_or_default();
+        let steps = self.macros.get(name).to_vec();
+        if steps.is_empty() {
+            let message = if name.is_empty() {
+                String::from("No macro has been recorded.")
+            } else {
+                format!("No macro named {name}.")
+            };
+            self.update_message(Severity::Info, &message);
+            return;
+        }
+        for _ in 0..count {
+            for step in steps.iter().filter(|step| !step.is_macro_command()) {
+                self.process_step(step);
+            }
+            if self.should_quit {
+                return;
+            }
+        }
+    }
+
+    fn handle_paste(&mut self, text: &str) {
+        self.reset_quit_times();
+        if self.prompt_type == PromptType::None {
+            if !self.refuse_if_read_only() {
+                self.view.insert_str(text);
+            }
+        } else {
+            self.command_bar.insert_str(text);
+            self.search_for_prompt_value();
+        }
+    }
+
+    /// Searches as the user types into the search prompt or the file finder.
+    fn search_for_prompt_value(&mut self) {
+        match self.prompt_type {
+            PromptType::Find => self.view.search(&self.command_bar.value()),
+            PromptType::FindFile => self.file_finder.set_query(&self.command_bar.value()),
+            _ => {}
+        }
+    }
+
+    /// Handles commands which edit the prompt or move its caret. Returns whether `command` was one of them.
+    fn process_prompt_command(&mut self, command: &str) -> bool {
+        let rules = self.settings.word_rules();
+        let value_before = self.command_bar.value();
+        match command {
+            "move_left" => self.command_bar.move_left(),
+            "move_right" => self.command_bar.move_right(),
+            "move_word_left" => self.command_bar.move_word_left(rules),
+            "move_word_right" => self.command_bar.move_word_right(rules),
+            "to_start_of_the_line" => self.command_bar.move_to_start(),
+            "to_end_of_the_file" => self.command_bar.move_to_end(),
+            "delete" => self.command_bar.delete(),
+            "delete_backward" => self.command_bar.delete_backward(),
+            "delete_word" => self.command_bar.delete_word(rules),
+            "delete_word_backward" => self.command_bar.delete_word_backward(rules),
+            "kill_to_start" => self.command_bar.kill_to_start(),
+            "kill_to_end" => self.command_bar.kill_to_end(),
+            "move_up" if self.prompt_type == PromptType::Find => self.view.search_prev(),
+            "move_down" if self.prompt_type == PromptType::Find => self.view.search_next(),
+            "move_up" if self.prompt_type == PromptType::FindFile => self.file_finder.select_previous(1),
+            "move_down" if self.prompt_type == PromptType::FindFile => self.file_finder.select_next(1),
+            "page_up" if self.prompt_type == PromptType::FindFile => {
+                self.file_finder.select_previous(self.view_size().height.saturating_sub(1));
+            }
+            "page_down" if self.prompt_type == PromptType::FindFile => {
+                self.file_finder.select_next(self.view_size().height.saturating_sub(1));
+            }
+            // Up and Down browse the history in prompts which don't use them otherwise
+            "history_previous" | "move_up" => self.command_bar.history_previous(),
+            "history_next" | "move_down" => self.command_bar.history_next(),
+            "tab" => {
+                if self.prompt_type.completes_paths() {
+                    self.command_bar.complete_path();
                 }
             }
-            Event::Resize(width, height) => {
-                self.resize(Size {
-                    width: width as usize,
-                    height: height as usize,
+            _ => return false,
+        }
+        if self.command_bar.value() != value_before {
+            self.search_for_prompt_value();
+        }
+        true
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEvent) {
+        if self.prompt_type != PromptType::None {
+            return;
+        }
+        let position = Position {
+            col: usize::from(event.column),
+            row: usize::from(event.row),
+        };
+        let is_in_view = position.row < self.view.get_size().height;
+
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) if is_in_view => {
+                let is_double_click = self.last_click.is_some_and(|(time, last_position)| {
+                    last_position == position && time.elapsed() <= DOUBLE_CLICK_INTERVAL
                 });
+                if is_double_click {
+                    self.view.select_word_at(position);
+                    self.last_click = None;
+                } else {
+                    self.view.click(position);
+                    self.last_click = Some((Instant::now(), position));
+                }
             }
+            MouseEventKind::Drag(MouseButton::Left) => self.view.drag_to(position),
+            MouseEventKind::ScrollUp => self.view.scroll_viewport_up(MOUSE_SCROLL_LINES),
+            MouseEventKind::ScrollDown => self.view.scroll_viewport_down(MOUSE_SCROLL_LINES),
             _ => {}
         }
     }
 
-    fn process_command(&mut self, command: String) {
-        match command.as_str() {
-            "quit" => self.handle_quit(),
-            _ => self.reset_quit_times(),
+    fn process_command(&mut self, command: &str) {
+        if command == "quit" {
+            self.handle_quit();
+            return;
+        }
+        self.reset_quit_times();
+        if self.prompt_type != PromptType::None && self.process_prompt_command(command) {
+            return;
+        }
+        if self.prompt_type == PromptType::None
+            && self.view.directory().is_some()
+            && self.process_browser_command(command)
+        {
+            return;
+        }
+        if self.prompt_type == PromptType::None
+            && self.view.is_project_search()
+            && command == "insert_newline"
+        {
+            self.open_project_match();
+            return;
+        }
+        if self.prompt_type == PromptType::None
+            && Self::is_edit_command(command)
+            && self.refuse_if_read_only()
+        {
+            return;
         }
 
-        match command.as_str() {
+        match command {
             "save" => self.handle_save(),
-            "quit" => {}, // Already handled above
-            
+            "toggle_read_only" if self.prompt_type == PromptType::None => {
+                self.toggle_read_only();
+            }
+            "toggle_whitespace" => self.toggle_whitespace(),
+            "message_history" if self.prompt_type == PromptType::None => {
+                self.show_message_history();
+            }
+            "strip_trailing_whitespace" if self.prompt_type == PromptType::None => {
+                self.strip_trailing_whitespace();
+            }
+
             // Search/replace
             "find" => self.show_prompt(PromptType::Find),
-
-            // Navigation
-            "move_up" => {
-                if self.prompt_type == PromptType::Find {
-                    self.view.search_prev()
+            "goto_line" => self.show_prompt(PromptType::GotoLine),
+            "open" => self.show_prompt(PromptType::Open),
+            "find_file" => self.show_prompt(PromptType::FindFile),
+            "grep" => self.show_prompt(PromptType::Grep),
+            "filter" if self.prompt_type == PromptType::None => self.show_prompt(PromptType::Filter),
+            "record_macro" if self.prompt_type == PromptType::None => {
+                if self.macro_recording.is_some() {
+                    self.update_message(Severity::Info, "Already recording a macro. Alt-S stops recording.");
                 } else {
-                    self.view.move_up(1)
+                    self.show_prompt(PromptType::RecordMacro);
                 }
             }
-            "move_down" => {
-                if self.prompt_type == PromptType::Find {
-                    self.view.search_next();
+            "stop_macro" if self.prompt_type == PromptType::None => self.stop_macro_recording(),
+            "play_macro" if self.prompt_type == PromptType::None => {
+                if self.macro_recording.is_some() {
+                    self.update_message(Severity::Info, "Macros can't be played while recording one.");
                 } else {
-                    self.view.move_down(1)
+                    self.show_prompt(PromptType::PlayMacro);
                 }
-            },
-            "move_left" => {
-                if self.prompt_type == PromptType::Find {
-                    self.view.search_prev()
-                } else {
-                    self.view.move_left()
-                }
-            },
-            "move_right" => {
-                if self.prompt_type == PromptType::Find {
-                    self.view.search_next();
-                } else {
-                    self.view.move_right()
-                }
-            },
-            "page_up" => self.view.move_up(self.view.get_size().height.saturating_sub(1)),
-            "page_down" => self.view.move_down(self.view.get_size().height.saturating_sub(1)),
-            "to_start_of_the_line" => self.view.move_to_start_of_line(),
-            "to_end_of_the_file" => self.view.move_to_end_of_line(),
+            }
+            "next_file" if self.prompt_type == PromptType::None => self.switch_file(true),
+            "previous_file" if self.prompt_type == PromptType::None => self.switch_file(false),
+
+            _ if Self::is_navigation_command(command) => self.process_navigation_command(command),
             
             // Editing
-            "delete" => {
-                if self.prompt_type == PromptType::Find {
-                    self.command_bar.delete();
-                    let query = self.command_bar.value();
-                    self.view.search(&query);
-                } else {
-                    self.view.delete()
-                }
-            }
-            "delete_backward" => {
-                if self.prompt_type != PromptType::None {
-                    self.command_bar.delete_last();
-                    self.command_bar.redraw();
-                    if self.prompt_type == PromptType::Find {
-                        let query = self.command_bar.value();
-                        self.view.search(&query);
-                    }
-                } else {
-                    self.view.delete_backward();
-                }
-            }
-            "tab" => self.view.insert_char('\t'),
+            "delete" => self.view.delete(),
+            "delete_backward" => self.view.delete_backward(),
+            "delete_word" => self.view.delete_word(),
+            "delete_word_backward" => self.view.delete_word_backward(),
+            "tab" => self.view.insert_tab(),
             
             // Prompts
             "dismiss" => self.dismiss_prompt(),
@@ -275,48 +1074,185 @@ impl Editor {
     }
 
     fn handle_enter_press(&mut self) {
-        if self.prompt_type != PromptType::None {
+        if self.prompt_type == PromptType::None {
+            self.view.insert_newline();
+        } else {
             let value = self.command_bar.value().clone();
-            
+            if let Err(err) = self.command_bar.remember_value() {
+                self.update_message(
+                    Severity::Warning,
+                    &format!("Could not save the prompt history: {err}"),
+                );
+            }
+
+            if self.prompt_type == PromptType::PlayMacro {
+                // The macro has to run without the prompt, so that its steps don't end up in the prompt
+                self.close_prompt();
+                self.play_macro(&value);
+                return;
+            }
+            if self.prompt_type == PromptType::Filter {
+                // The message bar shows how to cancel the command while it runs
+                self.close_prompt();
+                if !value.is_empty() {
+                    self.filter_lines(&value);
+                }
+                return;
+            }
+
             match self.prompt_type {
                 PromptType::Save => self.save(Some(&value)),
                 PromptType::Find => self.view.exit_search(),
-                PromptType::None => unreachable!(),
+                PromptType::GotoLine => {
+                    if let Err(err) = value
+                        .parse::<GotoTarget>()
+                        .and_then(|target| self.view.goto(target))
+                    {
+                        self.update_message(Severity::Error, &err);
+                    }
+                }
+                PromptType::Open if !value.is_empty() => self.open_path(&value),
+                PromptType::Grep if !value.is_empty() => self.start_project_search(&value),
+                PromptType::Open | PromptType::Grep => {}
+                PromptType::FindFile => {
+                    match self.file_finder.selected_file().map(str::to_string) {
+                        Some(file) => {
+                            let path = self.project_root.join(file);
+                            self.open_path(&path.to_string_lossy());
+                        }
+                        None => self.update_message(Severity::Info, "No matching file."),
+                    }
+                }
+                PromptType::RecordMacro => self.start_macro_recording(&value),
+                _ if self.prompt_type.is_file_operation() => {
+                    let prompt_type = self.prompt_type.clone();
+                    self.run_file_operation(&prompt_type, &value);
+                }
+                _ => unreachable!(),
             }
             
-            self.command_bar.clear_value();
-            self.prompt_type = PromptType::None;
-            self.message_bar.set_needs_redraw(true);
-            self.view.set_needs_redraw(true);
-            self.status_bar.set_needs_redraw(true);
-        } else {
-            self.view.insert_newline();
+            self.close_prompt();
         }
     }
 
-    fn handle_view_updates(&mut self, command: String) {
-        if matches!(
-            command.as_str(),
+    fn handle_view_updates(&mut self, command: &str) {
+        if Self::is_navigation_command(command) {
+            self.view.clear_selection();
+            self.view.scroll_text_location_into_view();
+        }
+    }
+
+    fn is_navigation_command(command: &str) -> bool {
+        matches!(
+            command,
             "move_up" | "move_down" | 
             "move_left" | "move_right" |
+            "move_word_left" | "move_word_right" |
+            "matching_bracket" |
             "page_up" | "page_down" |
             "to_start_of_the_line" | "to_end_of_the_file"
-        ) {
-            self.view.scroll_text_location_into_view();
+        )
+    }
+
+    fn process_navigation_command(&mut self, command: &str) {
+        match command {
+            "move_up" => self.view.move_up(1),
+            "move_down" => self.view.move_down(1),
+            "move_left" => self.view.move_left(),
+            "move_right" => self.view.move_right(),
+            "move_word_left" => self.view.move_word_left(),
+            "move_word_right" => self.view.move_word_right(),
+            "matching_bracket" if self.prompt_type == PromptType::None => {
+                self.jump_to_matching_bracket();
+            }
+            "page_up" => self.view.move_up(self.view.get_size().height.saturating_sub(1)),
+            "page_down" => self.view.move_down(self.view.get_size().height.saturating_sub(1)),
+            "to_start_of_the_line" => self.view.move_to_start_of_line(),
+            "to_end_of_the_file" => self.view.move_to_end_of_line(),
+            _ => {}
+        }
+    }
+
+    fn jump_to_matching_bracket(&mut self) {
+        if !self.view.jump_to_matching_bracket() {
+            self.update_message(Severity::Info, "No matching bracket found.");
         }
     }
 
     fn show_prompt(&mut self, prompt_type: PromptType) {
         match prompt_type {
-            PromptType::Save => self.command_bar.set_prompt("Save as: "),
+            PromptType::Save => self
+                .command_bar
+                .set_prompt("Save as: ", prompt_type.history_kind()),
             PromptType::Find => {
-                self.command_bar
-                    .set_prompt("Search (Esc to cancel, Arrows to navigate): ");
+                self.command_bar.set_prompt(
+                    "Search (Esc to cancel, Up/Down to navigate): ",
+                    prompt_type.history_kind(),
+                );
                 self.view.enter_search();
             }
-            _ => return,
+            PromptType::GotoLine => self.command_bar.set_prompt(
+                "Go to line (line[:col], +n, -n, %n): ",
+                prompt_type.history_kind(),
+            ),
+            PromptType::Open => {
+                self.command_bar.set_prompt("Open: ", prompt_type.history_kind());
+                // Start from the browsed directory, so that completion lists its entries
+                if let Some(dir) = self.view.directory() {
+                    let dir = dir.join("").to_string_lossy().into_owned();
+                    self.command_bar.set_value(&dir);
+                }
+            }
+            PromptType::FindFile => {
+                self.command_bar
+                    .set_prompt("Find file: ", prompt_type.history_kind());
+                // Walking a large project takes a while, so the list fills in once it is done
+                self.file_finder.set_files(Vec::new());
+                self.project_files = Some(list_project_files(&self.project_root));
+            }
+            PromptType::Grep => self
+                .command_bar
+                .set_prompt("Search in files: ", prompt_type.history_kind()),
+            PromptType::RecordMacro => self
+                .command_bar
+                .set_prompt("Record macro (name, or empty for unnamed): ", None),
+            PromptType::PlayMacro => self.command_bar.set_prompt(
+                "Play macro ([count] [name]): ",
+                prompt_type.history_kind(),
+            ),
+            PromptType::Filter => {
+                let lines = self.view.selected_lines().len();
+                let prompt = if self.view.selection().is_some() {
+                    format!("Filter {lines} selected lines through: ")
+                } else {
+                    String::from("Filter the whole file through: ")
+                };
+                self.command_bar.set_prompt(&prompt, prompt_type.history_kind());
+            }
+            PromptType::CreateFile => self.command_bar.set_prompt("New file: ", None),
+            PromptType::CreateDirectory => self.command_bar.set_prompt("New directory: ", None),
+            PromptType::Rename | PromptType::Delete => {
+                let Some(entry) = self.selected_browser_entry() else {
+                    self.update_message(Severity::Info, "Select a file or directory first.");
+                    return;
+                };
+                let name = entry_name(&entry);
+                if prompt_type == PromptType::Rename {
+                    self.command_bar.set_prompt("Rename to: ", None);
+                    self.command_bar.set_value(&name);
+                } else if entry.is_dir() {
+                    self.command_bar.set_prompt(
+                        &format!("Delete {name}/ and everything in it? (y/n): "),
+                        None,
+                    );
+                } else {
+                    self.command_bar
+                        .set_prompt(&format!("Delete {name}? (y/n): "), None);
+                }
+            }
+            PromptType::None => return,
         }
-        
+
         self.command_bar.resize(Size {
             height: 1,
             width: self.terminal_size.width,
@@ -327,9 +1263,25 @@ impl Editor {
     fn dismiss_prompt(&mut self) {
         match self.prompt_type {
             PromptType::Find => self.view.dismiss_search(),
-            PromptType::Save => {},
+            PromptType::Save
+            | PromptType::GotoLine
+            | PromptType::Open
+            | PromptType::FindFile
+            | PromptType::Grep
+            | PromptType::CreateFile
+            | PromptType::CreateDirectory
+            | PromptType::Rename
+            | PromptType::Delete
+            | PromptType::RecordMacro
+            | PromptType::PlayMacro
+            | PromptType::Filter => {}
+            PromptType::None if self.view.is_scratch() => self.close_scratch_view(),
             PromptType::None => self.handle_quit(),
         }
+        self.close_prompt();
+    }
+
+    fn close_prompt(&mut self) {
         self.command_bar.clear_value();
         self.prompt_type = PromptType::None;
         self.message_bar.set_needs_redraw(true);
@@ -337,11 +1289,109 @@ impl Editor {
         self.status_bar.set_needs_redraw(true);
     }
 
-    fn update_message(&mut self, new_message: &str) {
-        self.message_bar.update_message(new_message);
+    fn update_message(&mut self, severity: Severity, new_message: &str) {
+        let duration = self.settings.message_timeout(severity);
+        self.message_bar
+            .update_message(severity, new_message, duration);
+    }
+
+    fn is_edit_command(command: &str) -> bool {
+        matches!(
+            command,
+            "delete" | "delete_backward" |
+            "delete_word" | "delete_word_backward" |
+            "tab" | "insert_newline" | "filter" |
+            "strip_trailing_whitespace"
+        )
+    }
+
+    /// Shows a message and returns true if the current file must not be changed.
+    fn refuse_if_read_only(&mut self) -> bool {
+        if self.view.directory().is_some() {
+            self.update_message(
+                Severity::Warning,
+                "Directory listings can't be edited. n = new file | m = new directory | r = rename | d = delete",
+            );
+        } else if self.view.is_scratch() {
+            // Search results and the message history are generated, so they can't be made editable
+            let name = self.view.get_status().file_name;
+            self.update_message(Severity::Warning, &format!("{name} can't be edited."));
+        } else if self.view.is_read_only() {
+            self.update_message(
+                Severity::Warning,
+                "File is read-only. Press Ctrl-R to allow editing.",
+            );
+        }
+        self.view.is_read_only()
+    }
+
+    fn toggle_read_only(&mut self) {
+        if self.view.is_scratch() {
+            self.refuse_if_read_only();
+            return;
+        }
+        let read_only = !self.view.is_read_only();
+        self.view.set_read_only(read_only);
+        if read_only {
+            self.update_message(Severity::Info, "Read-only mode enabled.");
+        } else {
+            self.update_message(Severity::Info, "Read-only mode disabled.");
+        }
+        self.refresh_status();
+    }
+
+    fn toggle_whitespace(&mut self) {
+        self.settings.show_whitespace = !self.settings.show_whitespace;
+        self.view.set_settings(self.settings.clone());
+        for view in &mut self.other_views {
+            view.set_settings(self.settings.clone());
+        }
+    }
+
+    fn strip_trailing_whitespace(&mut self) {
+        match self.view.strip_trailing_whitespace() {
+            0 => self.update_message(Severity::Info, "No trailing whitespace found."),
+            1 => self.update_message(Severity::Info, "Removed trailing whitespace from 1 line."),
+            lines => self.update_message(
+                Severity::Info,
+                &format!("Removed trailing whitespace from {lines} lines."),
+            ),
+        }
+    }
+
+    /// Pipes the selected lines, or the whole file, through the shell command `command` and
+    /// replaces them with what it prints. Nothing changes if the command fails.
+    fn filter_lines(&mut self, command: &str) {
+        let lines = self.view.selected_lines();
+        let input = self.view.lines_text(lines.clone());
+        // Nothing is drawn while the command runs, so say what the editor is waiting for
+        self.update_message(
+            Severity::Info,
+            &format!("Running `{command}`... Press Esc to cancel."),
+        );
+        self.refresh_screen();
+        match run_filter(command, &input, is_esc_pressed) {
+            Ok(FilterOutput { stdout, stderr }) => {
+                let count = lines.len();
+                self.view.replace_lines(lines, &stdout);
+                if stderr.is_empty() {
+                    let noun = if count == 1 { "line" } else { "lines" };
+                    self.update_message(
+                        Severity::Info,
+                        &format!("Filtered {count} {noun} through `{command}`."),
+                    );
+                } else {
+                    self.update_message(Severity::Warning, &format!("`{command}`: {stderr}"));
+                }
+            }
+            Err(err) => self.update_message(Severity::Error, &err),
+        }
     }
 
     fn handle_save(&mut self) {
+        if self.refuse_if_read_only() {
+            return;
+        }
         if self.view.is_file_loaded() {
             self.save(None);
         } else {
@@ -351,26 +1401,33 @@ impl Editor {
 
     fn save(&mut self, file_name: Option<&str>) {
         let result = if let Some(name) = file_name {
-            self.view.save_as(name)
+            // Completion offers paths in the home directory as `~/...`
+            let Some(path) = expand_dir(name) else {
+                self.update_message(Severity::Error, "Could not find the home directory.");
+                return;
+            };
+            self.view.save_as(&path.to_string_lossy())
         } else {
             self.view.save()
         };
-        if result.is_ok() {
-            self.update_message("File saved successfully.");
-        } else {
-            self.update_message("Error writing file!");
+        match result {
+            Ok(()) => self.update_message(Severity::Info, "File saved successfully."),
+            Err(err) => self.update_message(Severity::Error, &format!("Error writing file: {err}")),
         }
     }
 
     #[allow(clippy::arithmetic_side_effects)]
     fn handle_quit(&mut self) {
-        if !self.view.get_status().is_modified || self.quit_times + 1 == QUIT_TIMES {
+        if !self.has_unsaved_changes() || self.quit_times + 1 == QUIT_TIMES {
             self.should_quit = true;
-        } else if self.view.get_status().is_modified {
-            self.update_message(&format!(
-                "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
-                QUIT_TIMES - self.quit_times - 1
-            ));
+        } else {
+            self.update_message(
+                Severity::Warning,
+                &format!(
+                    "There are unsaved changes. Press Ctrl-Q {} more times to quit.",
+                    QUIT_TIMES - self.quit_times - 1
+                ),
+            );
 
             self.quit_times += 1;
         }
@@ -379,7 +1436,7 @@ impl Editor {
     fn reset_quit_times(&mut self) {
         if self.quit_times > 0 {
             self.quit_times = 0;
-            self.message_bar.update_message("");
+            self.message_bar.clear_message();
         }
     }
 
@@ -391,45 +1448,35 @@ impl Editor {
         let _ = Terminal::hide_caret();
         let mut row = 0;
 
+        // Components are only resized on resize events, so that unchanged rows are not redrawn every frame
         let content_height = self.terminal_size.height.saturating_sub(2);
-        self.view.resize(Size {
-            width: self.terminal_size.width,
-            height: content_height,
-        });
-        self.view.render(row);
-        row += content_height;
+        if self.prompt_type == PromptType::FindFile {
+            self.file_finder.render(row);
+        } else {
+            self.view.update_bracket_pair();
+            self.view.render(row);
+        }
+        row = row.saturating_add(content_height);
 
         // Рендер статус-бара
-        self.status_bar.resize(Size {
-            width: self.terminal_size.width,
-            height: 1,
-        });
         self.status_bar.render(row);
-        row += 1;
+        row = row.saturating_add(1);
 
         // Рендер командной строки или сообщений
-        if self.prompt_type != PromptType::None {
-            self.command_bar.resize(Size {
-                width: self.terminal_size.width,
-                height: 1,
-            });
-            self.command_bar.render(row);
-        } else {
-            self.message_bar.resize(Size {
-                width: self.terminal_size.width,
-                height: 1,
-            });
+        if self.prompt_type == PromptType::None {
             self.message_bar.render(row);
+        } else {
+            self.command_bar.render(row);
         }
 
         // 3. Корректное позиционирование каретки
-        let caret_pos = if self.prompt_type != PromptType::None {
+        let caret_pos = if self.prompt_type == PromptType::None {
+            self.view.caret_position()
+        } else {
             Position {
                 row: self.terminal_size.height.saturating_sub(1),
                 col: self.command_bar.caret_position_col(),
             }
-        } else {
-            self.view.caret_position()
         };
 
         let _ = Terminal::move_caret_to(caret_pos);
@@ -438,6 +1485,22 @@ impl Editor {
     }
 }
 
+/// Whether Esc was pressed since the last check, dropping any other input.
+fn is_esc_pressed() -> bool {
+    let mut is_pressed = false;
+    while matches!(poll(Duration::ZERO), Ok(true)) {
+        if let Ok(Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            kind: KeyEventKind::Press,
+            ..
+        })) = read()
+        {
+            is_pressed = true;
+        }
+    }
+    is_pressed
+}
+
 impl Drop for Editor {
     fn drop(&mut self) {
         let _ = Terminal::terminate();
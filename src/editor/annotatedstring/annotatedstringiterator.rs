@@ -19,11 +19,11 @@ impl<'a> Iterator for AnnotatedStringIterator<'a> {
             .annotated_string
             .annotations
             .iter()
-            .filter(|annotation| {
+            .rev()
+            .find(|annotation| {
                 annotation.start_byte_idx <= self.current_idx
                     && annotation.end_byte_idx > self.current_idx
             })
-            .last()
         {
             let end_idx = min(annotation.end_byte_idx, self.annotated_string.string.len());
             let start_idx = self.current_idx;
@@ -2,4 +2,13 @@
 pub enum AnnotationType {
     Match,
     SelectedMatch,
+    Selection,
+    MatchingBracket,
+    Whitespace,
+    TrailingWhitespace,
+    StatusBar,
+    Gutter,
+    InfoMessage,
+    WarningMessage,
+    ErrorMessage,
 }
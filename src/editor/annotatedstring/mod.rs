@@ -39,6 +39,13 @@ impl AnnotatedString {
         });
     }
 
+    /// Adds `string` to the end, annotated with `annotation_type`.
+    pub fn append(&mut self, string: &str, annotation_type: AnnotationType) {
+        let start_byte_idx = self.string.len();
+        self.string.push_str(string);
+        self.add_annotation(annotation_type, start_byte_idx, self.string.len());
+    }
+
     pub fn replace(&mut self, start_byte_idx: usize, end_byte_idx: usize, new_string: &str) {
         debug_assert!(start_byte_idx <= end_byte_idx);
 
@@ -64,18 +71,11 @@ impl AnnotatedString {
                 } else {
                     annotation.start_byte_idx.saturating_add(len_difference)
                 }
-            } else if annotation.start_byte_idx >= start_byte_idx {
-                if shortened {
-                    max(
-                        start_byte_idx,
-                        annotation.start_byte_idx.saturating_sub(len_difference),
-                    )
-                } else {
-                    min(
-                        end_byte_idx,
-                        annotation.start_byte_idx.saturating_add(len_difference),
-                    )
-                }
+            } else if annotation.start_byte_idx >= start_byte_idx && shortened {
+                max(
+                    start_byte_idx,
+                    annotation.start_byte_idx.saturating_sub(len_difference),
+                )
             } else {
                 annotation.start_byte_idx
             };
@@ -86,7 +86,7 @@ impl AnnotatedString {
                 } else {
                     annotation.end_byte_idx.saturating_add(len_difference)
                 }
-            } else if annotation.end_byte_idx >= start_byte_idx {
+            } else if annotation.end_byte_idx > start_byte_idx {
                 if shortened {
                     max(
                         start_byte_idx,
@@ -127,3 +127,62 @@ impl<'a> IntoIterator for &'a AnnotatedString {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AnnotatedString, AnnotationType};
+
+    /// The string with its annotations, which are taken to be `Match` annotations.
+    fn annotated(string: &str, ranges: &[(usize, usize)]) -> AnnotatedString {
+        let mut annotated = AnnotatedString::from(string);
+        for &(start, end) in ranges {
+            annotated.add_annotation(AnnotationType::Match, start, end);
+        }
+        annotated
+    }
+
+    fn ranges(annotated: &AnnotatedString) -> Vec<(usize, usize)> {
+        annotated
+            .annotations
+            .iter()
+            .map(|annotation| (annotation.start_byte_idx, annotation.end_byte_idx))
+            .collect()
+    }
+
+    #[test]
+    fn shortening_moves_later_annotations_back() {
+        // "abcdefgh" with "cdef" replaced by "x"
+        let mut annotated = annotated("abcdefgh", &[(0, 2), (6, 8), (1, 4), (3, 7), (3, 5)]);
+        annotated.replace(2, 6, "x");
+        assert_eq!(annotated.to_string(), "abxgh");
+        // Annotations ending inside the replaced text end at its start, those starting inside it
+        // start there, and those entirely inside of it are gone
+        assert_eq!(ranges(&annotated), vec![(0, 2), (3, 5), (1, 2), (2, 4)]);
+    }
+
+    #[test]
+    fn lengthening_moves_later_annotations_on() {
+        // "a\tb" with the tab expanded to four spaces
+        let mut annotated = annotated("a\tb", &[(0, 1), (2, 3), (0, 2)]);
+        annotated.replace(1, 2, "    ");
+        assert_eq!(annotated.to_string(), "a    b");
+        assert_eq!(ranges(&annotated), vec![(0, 1), (5, 6), (0, 5)]);
+    }
+
+    #[test]
+    fn lengthening_keeps_annotations_starting_at_the_replaced_text_on_it() {
+        // A whitespace annotation on a tab has to cover all the columns the tab is expanded to
+        let mut annotated = annotated("a\tb", &[(1, 2)]);
+        annotated.replace(1, 2, "→   ");
+        assert_eq!(annotated.to_string(), "a→   b");
+        assert_eq!(ranges(&annotated), vec![(1, "a→   ".len())]);
+    }
+
+    #[test]
+    fn annotations_covering_the_replaced_text_cover_its_replacement() {
+        let mut annotated = annotated("a b", &[(1, 2), (0, 3)]);
+        annotated.replace(1, 2, "·");
+        assert_eq!(annotated.to_string(), "a·b");
+        assert_eq!(ranges(&annotated), vec![(1, 3), (0, 4)]);
+    }
+}
@@ -0,0 +1,327 @@
+use std::path::Path;
+
+use super::gototarget::parse_line_col;
+use super::prelude::{NAME, VERSION};
+
+pub const USAGE: &str = "\
+Usage: stack [OPTIONS] [+LINE[:COL] | +/PATTERN] [FILE[:LINE[:COL]]]...
+
+Opens each FILE in the editor. Use `-` as FILE to edit text read from standard input.
+Ctrl-PageDown and Ctrl-PageUp switch between the given files.
+
+Arguments:
+  +LINE[:COL]   Place the caret at LINE (and COL) of the file which follows
+  +/PATTERN     Place the caret at the first match of PATTERN in the file which follows
+  FILE:LINE:COL Open FILE at LINE and COL, as printed by compilers
+
+Options:
+  -R, --readonly      Open the files read-only
+  -b, --batch SCRIPT  Run the commands in SCRIPT (`-` for standard input) on FILE and exit
+  -h, --help          Print this help and exit
+  -V, --version       Print the version and exit
+
+Batch scripts hold one command per line, stopping at the first one which fails:
+  goto LINE[:COL]     Move the caret, also `+N`, `-N` and `%N` as in the editor
+  find TEXT           Move the caret behind the next occurrence of TEXT
+  replace TEXT WITH   Replace every occurrence of TEXT
+  insert TEXT         Insert TEXT at the caret
+  delete_line         Delete the line at the caret
+  save [FILE]         Save the changes, to FILE if given
+Arguments containing blanks are written in double quotes, which understand `\\n`, `\\t`, `\\\\` and `\\\"`.";
+
+/// Where to place the caret after opening a file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StartPosition {
+    Location { line: usize, col: Option<usize> },
+    Pattern(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FileArgument {
+    Path(String),
+    Stdin,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpenFile {
+    pub file: FileArgument,
+    pub start_position: Option<StartPosition>,
+}
+
+#[derive(Default, Debug)]
+pub struct Arguments {
+    pub files: Vec<OpenFile>,
+    pub read_only: bool,
+}
+
+/// A script to run on a file without opening the editor.
+#[derive(Debug)]
+pub struct BatchArguments {
+    /// The path of the script, or `-` for standard input
+    pub script: String,
+    pub file: String,
+    /// Whether commands which change the file are refused
+    pub read_only: bool,
+}
+
+/// What the binary was asked to do.
+#[derive(Debug)]
+pub enum Invocation {
+    Edit(Arguments),
+    Batch(BatchArguments),
+    Help,
+    Version,
+}
+
+impl Invocation {
+    pub fn version() -> String {
+        format!("{NAME} {VERSION}")
+    }
+}
+
+impl Arguments {
+    /// Parses the command line arguments, excluding the binary name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Invocation, String> {
+        let mut arguments = Self::default();
+        let mut pending_position: Option<(String, StartPosition)> = None;
+        let mut only_files = false;
+        let mut batch_script = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !only_files {
+                match arg.as_str() {
+                    "-h" | "--help" => return Ok(Invocation::Help),
+                    "-V" | "--version" => return Ok(Invocation::Version),
+                    "-R" | "--readonly" => {
+                        arguments.read_only = true;
+                        continue;
+                    }
+                    "-b" | "--batch" => {
+                        let script = args
+                            .next()
+                            .ok_or_else(|| format!("`{arg}` needs a script to run"))?;
+                        batch_script = Some(script);
+                        continue;
+                    }
+                    "--" => {
+                        only_files = true;
+                        continue;
+                    }
+                    _ => {}
+                }
+                if let Some(position) = arg.strip_prefix('+') {
+                    let start_position = Self::parse_start_position(position)
+                        .map_err(|err| format!("invalid argument `{arg}`: {err}"))?;
+                    pending_position = Some((arg.clone(), start_position));
+                    continue;
+                }
+                if arg.len() > 1 && arg.starts_with('-') {
+                    return Err(format!("unknown option `{arg}`"));
+                }
+            }
+
+            let mut open_file = if arg == "-" {
+                if arguments
+                    .files
+                    .iter()
+                    .any(|open_file| open_file.file == FileArgument::Stdin)
+                {
+                    return Err(String::from("standard input (`-`) can only be read once"));
+                }
+                OpenFile {
+                    file: FileArgument::Stdin,
+                    start_position: None,
+                }
+            } else {
+                Self::parse_file(&arg)
+            };
+            if let Some((_, start_position)) = pending_position.take() {
+                open_file.start_position = Some(start_position);
+            }
+            arguments.files.push(open_file);
+        }
+
+        if let Some((arg, start_position)) = pending_position {
+            // A trailing position applies to the last file, as in `stack main.rs +42`
+            let last_file = arguments
+                .files
+                .last_mut()
+                .ok_or_else(|| format!("`{arg}` needs a file to apply to"))?;
+            last_file.start_position = Some(start_position);
+        }
+        if let Some(script) = batch_script {
+            return Self::into_batch(arguments, script);
+        }
+        Ok(Invocation::Edit(arguments))
+    }
+
+    /// Checks that the arguments fit a batch run, which edits exactly one file.
+    fn into_batch(self, script: String) -> Result<Invocation, String> {
+        let [OpenFile {
+            file: FileArgument::Path(file),
+            start_position: None,
+        }] = <[OpenFile; 1]>::try_from(self.files)
+            .map_err(|_| String::from("`--batch` needs exactly one file to edit"))?
+        else {
+            return Err(String::from("`--batch` needs the path of a file, without a position"));
+        };
+        Ok(Invocation::Batch(BatchArguments {
+            script,
+            file,
+            read_only: self.read_only,
+        }))
+    }
+
+    fn parse_start_position(position: &str) -> Result<StartPosition, String> {
+        if let Some(pattern) = position.strip_prefix('/') {
+            if pattern.is_empty() {
+                return Err(String::from("empty search pattern"));
+            }
+            return Ok(StartPosition::Pattern(pattern.to_string()));
+        }
+        let (line, col) = parse_line_col(position)?;
+        Ok(StartPosition::Location { line, col })
+    }
+
+    /// Splits off a `:line[:col]` suffix, unless a file with the full name exists.
+    fn parse_file(arg: &str) -> OpenFile {
+        let at = |path: &str, line: usize, col: Option<usize>| OpenFile {
+            file: FileArgument::Path(path.to_string()),
+            start_position: Some(StartPosition::Location { line, col }),
+        };
+        if !Path::new(arg).exists() {
+            if let Some((rest, last)) = arg.rsplit_once(':') {
+                if let Some((path, line)) = rest.rsplit_once(':') {
+                    if let (Ok(line), Ok(col), false) = (line.parse(), last.parse(), path.is_empty()) {
+                        return at(path, line, Some(col));
+                    }
+                }
+                if let (Ok(line), false) = (last.parse(), rest.is_empty()) {
+                    return at(rest, line, None);
+                }
+            }
+        }
+        OpenFile {
+            file: FileArgument::Path(arg.to_string()),
+            start_position: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::{Arguments, FileArgument, Invocation, OpenFile, StartPosition};
+
+    fn parse(args: &[&str]) -> Result<Invocation, String> {
+        Arguments::parse(args.iter().map(ToString::to_string))
+    }
+
+    fn files(args: &[&str]) -> Vec<OpenFile> {
+        match parse(args) {
+            Ok(Invocation::Edit(arguments)) => arguments.files,
+            other => panic!("expected files to edit, got {other:?}"),
+        }
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse(args).expect_err("expected an error")
+    }
+
+    fn path(path: &str, start_position: Option<StartPosition>) -> OpenFile {
+        OpenFile {
+            file: FileArgument::Path(path.to_string()),
+            start_position,
+        }
+    }
+
+    fn at(line: usize, col: Option<usize>) -> StartPosition {
+        StartPosition::Location { line, col }
+    }
+
+    #[test]
+    fn positions_apply_to_the_file_which_follows() {
+        assert_eq!(files(&["+12", "a.rs", "b.rs"]), [path("a.rs", Some(at(12, None))), path("b.rs", None)]);
+        assert_eq!(files(&["a.rs", "+3:7", "b.rs"]), [path("a.rs", None), path("b.rs", Some(at(3, Some(7))))]);
+        assert_eq!(
+            files(&["+/fn main", "a.rs"]),
+            [path("a.rs", Some(StartPosition::Pattern(String::from("fn main"))))]
+        );
+        // Unless no file follows
+        assert_eq!(files(&["a.rs", "+12"]), [path("a.rs", Some(at(12, None)))]);
+    }
+
+    #[test]
+    fn invalid_positions_are_refused() {
+        assert_eq!(error(&["+12"]), "`+12` needs a file to apply to");
+        assert_eq!(error(&["+/", "a.rs"]), "invalid argument `+/`: empty search pattern");
+        assert!(error(&["+x", "a.rs"]).starts_with("invalid argument `+x`: "));
+        assert!(error(&["+1:x", "a.rs"]).starts_with("invalid argument `+1:x`: "));
+    }
+
+    #[test]
+    fn line_and_column_suffixes_are_split_off() {
+        assert_eq!(files(&["src/main.rs:12:3"]), [path("src/main.rs", Some(at(12, Some(3))))]);
+        assert_eq!(files(&["src/main.rs:12"]), [path("src/main.rs", Some(at(12, None)))]);
+        assert_eq!(files(&["notes:todo"]), [path("notes:todo", None)]);
+        assert_eq!(files(&[":12"]), [path(":12", None)]);
+        assert_eq!(files(&["a:b:3"]), [path("a:b", Some(at(3, None)))]);
+    }
+
+    #[test]
+    fn existing_files_keep_their_full_name() {
+        let dir = env::temp_dir().join(format!("stack-arguments-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("log:12");
+        fs::write(&file, "").unwrap();
+        let name = file.to_string_lossy().into_owned();
+        let parsed = files(&[&name]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(parsed, [path(&name, None)]);
+    }
+
+    #[test]
+    fn a_dash_reads_standard_input_once() {
+        let stdin = OpenFile {
+            file: FileArgument::Stdin,
+            start_position: None,
+        };
+        assert_eq!(files(&["a.rs", "-"]), [path("a.rs", None), stdin]);
+        assert_eq!(error(&["-", "-"]), "standard input (`-`) can only be read once");
+    }
+
+    #[test]
+    fn arguments_after_a_double_dash_are_files() {
+        assert_eq!(
+            files(&["--", "-R", "+12", "--help"]),
+            [path("-R", None), path("+12", None), path("--help", None)]
+        );
+        let Ok(Invocation::Edit(arguments)) = parse(&["-R", "--", "-R"]) else {
+            panic!("expected files to edit");
+        };
+        assert!(arguments.read_only);
+        assert_eq!(arguments.files, [path("-R", None)]);
+    }
+
+    #[test]
+    fn unknown_options_are_refused() {
+        assert_eq!(error(&["-x", "a.rs"]), "unknown option `-x`");
+        assert_eq!(error(&["a.rs", "--read-only"]), "unknown option `--read-only`");
+        assert!(matches!(parse(&["a.rs", "--help", "-x"]), Ok(Invocation::Help)));
+        assert!(matches!(parse(&["-V"]), Ok(Invocation::Version)));
+    }
+
+    #[test]
+    fn batch_runs_take_exactly_one_file() {
+        let Ok(Invocation::Batch(batch)) = parse(&["-R", "--batch", "script", "a.rs"]) else {
+            panic!("expected a batch run");
+        };
+        assert_eq!((batch.script.as_str(), batch.file.as_str(), batch.read_only), ("script", "a.rs", true));
+        assert_eq!(error(&["--batch"]), "`--batch` needs a script to run");
+        assert_eq!(error(&["-b", "script"]), "`--batch` needs exactly one file to edit");
+        assert_eq!(error(&["-b", "script", "a.rs", "b.rs"]), "`--batch` needs exactly one file to edit");
+        assert_eq!(error(&["-b", "script", "a.rs:3"]), "`--batch` needs the path of a file, without a position");
+    }
+}
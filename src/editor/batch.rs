@@ -0,0 +1,334 @@
+use std::{
+    fs::read_to_string,
+    io::{stdin, Read},
+};
+
+use super::arguments::BatchArguments;
+use super::uicomponents::Buffer;
+use super::{GotoTarget, Line, Location};
+
+/// Runs the script given by `arguments` on its file, without touching the terminal.
+/// Stops at the first command which fails, returning what went wrong and where.
+pub fn run_batch(arguments: &BatchArguments) -> Result<(), String> {
+    let script = if arguments.script == "-" {
+        let mut script = String::new();
+        stdin()
+            .read_to_string(&mut script)
+            .map_err(|err| format!("could not read the script from standard input: {err}"))?;
+        script
+    } else {
+        read_to_string(&arguments.script)
+            .map_err(|err| format!("could not read {}: {err}", arguments.script))?
+    };
+    let buffer = Buffer::load(&arguments.file)
+        .map_err(|err| format!("could not open {}: {err}", arguments.file))?;
+    let mut batch = Batch {
+        buffer,
+        caret: Location::default(),
+        file: arguments.file.clone(),
+    };
+    if arguments.read_only {
+        batch.buffer.read_only = true;
+    }
+
+    for (line_idx, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        split_words(line)
+            .and_then(|words| batch.run(&words))
+            .map_err(|err| format!("{}:{}: {err}", arguments.script, line_idx.saturating_add(1)))?;
+    }
+    Ok(())
+}
+
+/// A file being edited by a script.
+struct Batch {
+    buffer: Buffer,
+    caret: Location,
+    /// The name the file was opened with, for messages
+    file: String,
+}
+
+impl Batch {
+    fn run(&mut self, words: &[String]) -> Result<(), String> {
+        let Some((command, args)) = words.split_first() else {
+            return Ok(());
+        };
+        match (command.as_str(), args) {
+            ("goto", [target]) => {
+                let target = target.parse::<GotoTarget>()?;
+                self.caret = self.buffer.locate(target, self.caret)?;
+            }
+            ("find", [query]) => self.find(query)?,
+            ("replace", [query, replacement]) => self.replace(query, replacement)?,
+            ("insert", [text]) => {
+                self.check_writable()?;
+                self.caret = self.buffer.insert_str(text, self.caret);
+            }
+            ("delete_line", []) => {
+                self.check_writable()?;
+                if self.caret.line_idx >= self.buffer.height() {
+                    return Err(String::from("there is no line to delete"));
+                }
+                self.buffer.delete_line(self.caret.line_idx);
+                self.caret = Location {
+                    grapheme_idx: 0,
+                    line_idx: self.caret.line_idx.min(self.buffer.height().saturating_sub(1)),
+                };
+            }
+            ("save", []) => self
+                .buffer
+                .save()
+                .map_err(|err| format!("could not save {}: {err}", self.file))?,
+            ("save", [file]) => self
+                .buffer
+                .save_as(file)
+                .map_err(|err| format!("could not save {file}: {err}"))?,
+            ("goto" | "find" | "insert", _) => return Err(format!("`{command}` takes one argument")),
+            ("replace", _) => return Err(String::from("`replace` takes two arguments")),
+            ("delete_line", _) => return Err(String::from("`delete_line` takes no arguments")),
+            ("save", _) => return Err(String::from("`save` takes at most one argument")),
+            _ => return Err(format!("unknown command `{command}`")),
+        }
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<(), String> {
+        if self.buffer.read_only {
+            return Err(format!("{} is read-only", self.file));
+        }
+        Ok(())
+    }
+
+    /// Finds the next occurrence of `query` at or after `from`, without wrapping around.
+    fn search_from(&self, query: &str, from: Location) -> Option<Location> {
+        self.buffer
+            .search_forward(query, from)
+            .filter(|&found| found >= from)
+    }
+
+    fn find(&mut self, query: &str) -> Result<(), String> {
+        check_query(query)?;
+        let found = self
+            .search_from(query, self.caret)
+            .ok_or_else(|| format!("`{query}` was not found"))?;
+        self.caret = Location {
+            grapheme_idx: found
+                .grapheme_idx
+                .saturating_add(Line::from(query).grapheme_count()),
+            line_idx: found.line_idx,
+        };
+        Ok(())
+    }
+
+    fn replace(&mut self, query: &str, replacement: &str) -> Result<(), String> {
+        check_query(query)?;
+        self.check_writable()?;
+        let query_len = Line::from(query).grapheme_count();
+        let mut from = Location::default();
+        let mut count: usize = 0;
+        while let Some(found) = self.search_from(query, from) {
+            let end = Location {
+                grapheme_idx: found.grapheme_idx.saturating_add(query_len),
+                line_idx: found.line_idx,
+            };
+            self.buffer.delete_range(found, end);
+            // Continue behind the replacement, so that it isn't replaced again
+            from = self.buffer.insert_str(replacement, found);
+            count = count.saturating_add(1);
+        }
+        if count == 0 {
+            return Err(format!("`{query}` was not found"));
+        }
+        self.snap_caret();
+        Ok(())
+    }
+
+    /// Moves the caret back into the text after lines got shorter.
+    fn snap_caret(&mut self) {
+        let line_idx = self
+            .caret
+            .line_idx
+            .min(self.buffer.height().saturating_sub(1));
+        let grapheme_count = self
+            .buffer
+            .lines
+            .get(line_idx)
+            .map_or(0, Line::grapheme_count);
+        self.caret = Location {
+            grapheme_idx: self.caret.grapheme_idx.min(grapheme_count),
+            line_idx,
+        };
+    }
+}
+
+fn check_query(query: &str) -> Result<(), String> {
+    if query.is_empty() {
+        return Err(String::from("empty search text"));
+    }
+    Ok(())
+}
+
+/// Splits a script line into words at blanks. Double quotes group blanks into a word and
+/// understand the escapes `\n`, `\t`, `\\` and `\"`.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => word.push('\n'),
+                        Some('t') => word.push('\t'),
+                        Some(escaped @ ('\\' | '"')) => word.push(escaped),
+                        Some(other) => return Err(format!("unknown escape `\\{other}`")),
+                        None => return Err(String::from("unterminated quote")),
+                    },
+                    Some(other) => word.push(other),
+                    None => return Err(String::from("unterminated quote")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::{run_batch, split_words, Batch, BatchArguments, Buffer, GotoTarget, Location};
+
+    fn batch(contents: &str) -> Batch {
+        Batch {
+            buffer: Buffer::from_contents(contents),
+            caret: Location::default(),
+            file: String::from("test.txt"),
+        }
+    }
+
+    fn run(batch: &mut Batch, line: &str) -> Result<(), String> {
+        split_words(line).and_then(|words| batch.run(&words))
+    }
+
+    fn text(batch: &Batch) -> String {
+        let lines: Vec<String> = batch.buffer.lines.iter().map(ToString::to_string).collect();
+        lines.join("\n")
+    }
+
+    #[test]
+    fn words_are_split_at_blanks_outside_of_quotes() {
+        assert_eq!(
+            split_words(r#"  replace "a b" c\d  "" "#),
+            Ok(vec![
+                String::from("replace"),
+                String::from("a b"),
+                String::from(r"c\d"),
+                String::new(),
+            ])
+        );
+        assert_eq!(
+            split_words(r#"insert "tab\tquote\"line\nslash\\""#),
+            Ok(vec![String::from("insert"), String::from("tab\tquote\"line\nslash\\")])
+        );
+        assert_eq!(split_words(r#"insert "\x""#), Err(String::from(r"unknown escape `\x`")));
+        assert_eq!(split_words(r#"insert "open"#), Err(String::from("unterminated quote")));
+    }
+
+    #[test]
+    fn goto_targets_are_located_relative_to_the_caret() {
+        let buffer = Buffer::from_contents("one\ntwo\nthree\nfour\nfive");
+        let at = |line_idx, grapheme_idx| Location {
+            grapheme_idx,
+            line_idx,
+        };
+        let line = |line, col| GotoTarget::Line { line, col };
+        assert_eq!(buffer.locate(line(3, Some(2)), at(0, 0)), Ok(at(2, 1)));
+        assert_eq!(buffer.locate(line(2, Some(4)), at(0, 0)), Ok(at(1, 3)));
+        assert_eq!(buffer.locate(GotoTarget::Forward(2), at(1, 2)), Ok(at(3, 0)));
+        assert_eq!(buffer.locate(GotoTarget::Backward(3), at(3, 0)), Ok(at(0, 0)));
+        assert_eq!(buffer.locate(GotoTarget::Percent(50), at(0, 0)), Ok(at(2, 0)));
+        assert_eq!(
+            buffer.locate(line(6, None), at(0, 0)),
+            Err(String::from("Line out of range (1-5): 6"))
+        );
+        assert_eq!(
+            buffer.locate(line(1, Some(5)), at(0, 0)),
+            Err(String::from("Column out of range (1-4): 5"))
+        );
+        assert_eq!(
+            buffer.locate(GotoTarget::Backward(2), at(1, 0)),
+            Err(String::from("Cannot go back 2 lines from line 2"))
+        );
+    }
+
+    #[test]
+    fn replacements_containing_the_query_are_not_replaced_again() {
+        let mut batch = batch("a-a\nba");
+        run(&mut batch, "replace a aa").unwrap();
+        assert_eq!(text(&batch), "aa-aa\nbaa");
+    }
+
+    #[test]
+    fn replacements_and_insertions_can_span_lines() {
+        let mut batch = batch("one two\nthree");
+        run(&mut batch, r#"replace " " "\n""#).unwrap();
+        assert_eq!(text(&batch), "one\ntwo\nthree");
+
+        run(&mut batch, "goto 3:6").unwrap();
+        run(&mut batch, r#"insert "\nfour\nfive""#).unwrap();
+        assert_eq!(text(&batch), "one\ntwo\nthree\nfour\nfive");
+        assert_eq!(batch.caret, Location { grapheme_idx: 4, line_idx: 4 });
+    }
+
+    #[test]
+    fn empty_search_texts_are_refused() {
+        let mut batch = batch("text");
+        assert_eq!(run(&mut batch, r#"find """#), Err(String::from("empty search text")));
+        assert_eq!(
+            run(&mut batch, r#"replace "" x"#),
+            Err(String::from("empty search text"))
+        );
+    }
+
+    #[test]
+    fn failing_commands_stop_the_script_and_name_its_line() {
+        let dir = env::temp_dir().join(format!("stack-batch-tests-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file.txt");
+        let script = dir.join("script");
+        fs::write(&file, "alpha\nbeta\n").unwrap();
+        fs::write(&script, "# rename\nreplace alpha gamma\n\nfind delta\nsave\n").unwrap();
+
+        let arguments = BatchArguments {
+            script: script.to_string_lossy().into_owned(),
+            file: file.to_string_lossy().into_owned(),
+            read_only: false,
+        };
+        let result = run_batch(&arguments);
+        let contents = fs::read_to_string(&file).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(result, Err(format!("{}:4: `delta` was not found", arguments.script)));
+        assert_eq!(contents, "alpha\nbeta\n");
+    }
+}
@@ -19,10 +19,9 @@ impl Bindings {
             code, modifiers, ..
         } = event;
 
-        if !self.binds.contains_key(&(code, modifiers)) {
-            return Err(format!("bind {:?} not found!", (code, modifiers)));
-        };
-
-        Ok(self.binds.get(&(code, modifiers)).unwrap().to_string())
+        self.binds
+            .get(&(code, modifiers))
+            .cloned()
+            .ok_or_else(|| format!("bind {:?} not found!", (code, modifiers)))
     }
 }
@@ -2,8 +2,18 @@
 pub struct DocumentStatus {
     pub total_lines: usize,
     pub current_line_idx: usize,
+    pub current_grapheme_idx: usize,
+    /// The column on screen, which differs from the grapheme index after tabs and wide characters
+    pub current_col: usize,
+    pub selected_graphemes: usize,
+    pub selected_lines: usize,
     pub is_modified: bool,
+    pub is_read_only: bool,
     pub file_name: String,
+    pub file_type: String,
+    pub encoding: String,
+    pub line_ending: String,
+    pub mode: String,
 }
 
 impl DocumentStatus {
@@ -14,6 +24,20 @@ impl DocumentStatus {
             String::new()
         }
     }
+    pub fn read_only_indicator_to_string(&self) -> String {
+        if self.is_read_only {
+            String::from(" [RO]")
+        } else {
+            String::new()
+        }
+    }
+    pub fn selection_to_string(&self) -> String {
+        match self.selected_lines {
+            0 => String::new(),
+            1 => format!("{} selected", self.selected_graphemes),
+            lines => format!("{} selected ({lines} lines)", self.selected_graphemes),
+        }
+    }
     pub fn line_count_to_string(&self) -> String {
         format!("{} lines", self.total_lines)
     }
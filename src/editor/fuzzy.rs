@@ -0,0 +1,223 @@
+use std::cmp::{max, Reverse};
+
+use super::ByteIdx;
+
+const MATCH_SCORE: i32 = 16;
+const CONSECUTIVE_BONUS: i32 = 8;
+/// For a match at the start of a path component
+const PATH_START_BONUS: i32 = 10;
+/// For a match after a separator within a name, such as `_` or `.`
+const WORD_START_BONUS: i32 = 8;
+/// For an upper case match after a lower case letter
+const CAMEL_CASE_BONUS: i32 = 7;
+/// For a match within the file name rather than the directories leading to it
+const FILE_NAME_BONUS: i32 = 2;
+const GAP_START_PENALTY: i32 = 3;
+const GAP_EXTENSION_PENALTY: i32 = 1;
+
+/// How well a query matches a candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i32,
+    /// The byte indices of the characters of the candidate which were matched
+    pub positions: Vec<ByteIdx>,
+}
+
+/// Matches `query` against `candidate` if all characters of the query appear in the candidate in
+/// order. Matches at the start of words, consecutive matches and matches in the file name score
+/// higher, gaps between matched characters lower. The match ignores case unless the query
+/// contains upper case letters.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let chars: Vec<(ByteIdx, char)> = candidate.char_indices().collect();
+    let case_sensitive = query.iter().any(|c| c.is_uppercase());
+    let is_equal = |query_char: char, candidate_char: char| {
+        if case_sensitive {
+            query_char == candidate_char
+        } else {
+            query_char.to_lowercase().eq(candidate_char.to_lowercase())
+        }
+    };
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    // Rule out most candidates quickly, before the more expensive scoring
+    let mut remaining = chars.iter();
+    if !query
+        .iter()
+        .all(|&query_char| remaining.any(|&(_, c)| is_equal(query_char, c)))
+    {
+        return None;
+    }
+
+    let file_name_start = candidate.rfind('/').map_or(0, |idx| idx.saturating_add(1));
+    let bonuses: Vec<i32> = (0..chars.len())
+        .map(|idx| bonus(&chars, idx, file_name_start))
+        .collect();
+
+    // scores[i][j] is the best score of matching the first i + 1 query characters, with the last
+    // one matched at candidate character j
+    let mut scores: Vec<Vec<Option<i32>>> = Vec::with_capacity(query.len());
+    for (query_idx, &query_char) in query.iter().enumerate() {
+        let previous = query_idx.checked_sub(1).and_then(|idx| scores.get(idx));
+        let mut row = vec![None; chars.len()];
+        // The best score of the previous row at least two characters back, less the gap penalty
+        let mut gapped: Option<i32> = None;
+        for (idx, &(_, c)) in chars.iter().enumerate() {
+            if let Some(previous) = previous {
+                if let Some(before_last) = idx.checked_sub(2) {
+                    gapped = max(
+                        gapped.map(|score| score.saturating_sub(GAP_EXTENSION_PENALTY)),
+                        previous
+                            .get(before_last)
+                            .copied()
+                            .flatten()
+                            .map(|score| score.saturating_sub(GAP_START_PENALTY)),
+                    );
+                }
+            }
+            if !is_equal(query_char, c) {
+                continue;
+            }
+            let best_before = match previous {
+                None => Some(0),
+                Some(previous) => {
+                    let consecutive = idx
+                        .checked_sub(1)
+                        .and_then(|last| previous.get(last).copied().flatten())
+                        .map(|score| score.saturating_add(CONSECUTIVE_BONUS));
+                    max(consecutive, gapped)
+                }
+            };
+            if let (Some(best_before), Some(row_score)) = (best_before, row.get_mut(idx)) {
+                let bonus = bonuses.get(idx).copied().unwrap_or_default();
+                *row_score = Some(best_before.saturating_add(MATCH_SCORE).saturating_add(bonus));
+            }
+        }
+        scores.push(row);
+    }
+
+    let last_row = scores.last()?;
+    let (mut idx, score) = last_row
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, score)| score.map(|score| (idx, score)))
+        .max_by_key(|&(idx, score)| (score, Reverse(idx)))?;
+    // Walk back through the rows to find where the other query characters were matched
+    let mut positions = vec![idx];
+    for query_idx in (1..scores.len()).rev() {
+        let bonus = bonuses.get(idx).copied().unwrap_or_default();
+        let target = scores
+            .get(query_idx)
+            .and_then(|row| row.get(idx).copied().flatten())?
+            .saturating_sub(MATCH_SCORE)
+            .saturating_sub(bonus);
+        idx = previous_position(scores.get(query_idx.saturating_sub(1))?, idx, target)?;
+        positions.push(idx);
+    }
+    positions.reverse();
+    Some(FuzzyMatch {
+        score,
+        positions: positions
+            .into_iter()
+            .filter_map(|idx| chars.get(idx).map(|&(byte_idx, _)| byte_idx))
+            .collect(),
+    })
+}
+
+/// Finds the position in `row`, the scores of the previous query character, from which the match
+/// at `idx` reached `target`.
+fn previous_position(row: &[Option<i32>], idx: usize, target: i32) -> Option<usize> {
+    let last = idx.checked_sub(1)?;
+    if row
+        .get(last)
+        .copied()
+        .flatten()
+        .is_some_and(|score| score.saturating_add(CONSECUTIVE_BONUS) == target)
+    {
+        return Some(last);
+    }
+    let mut penalty = GAP_START_PENALTY;
+    for before in (0..last).rev() {
+        if row
+            .get(before)
+            .copied()
+            .flatten()
+            .is_some_and(|score| score.saturating_sub(penalty) == target)
+        {
+            return Some(before);
+        }
+        penalty = penalty.saturating_add(GAP_EXTENSION_PENALTY);
+    }
+    None
+}
+
+/// The bonus for matching the character at `idx`, depending on where in the path it is.
+fn bonus(chars: &[(ByteIdx, char)], idx: usize, file_name_start: ByteIdx) -> i32 {
+    let Some(&(byte_idx, c)) = chars.get(idx) else {
+        return 0;
+    };
+    let file_name_bonus = if byte_idx >= file_name_start {
+        FILE_NAME_BONUS
+    } else {
+        0
+    };
+    let position_bonus = match idx.checked_sub(1).and_then(|last| chars.get(last)) {
+        None | Some((_, '/')) => PATH_START_BONUS,
+        Some((_, '_' | '-' | '.' | ' ')) => WORD_START_BONUS,
+        Some((_, last)) if last.is_lowercase() && c.is_uppercase() => CAMEL_CASE_BONUS,
+        Some(_) => 0,
+    };
+    position_bonus.saturating_add(file_name_bonus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_match;
+
+    fn score(query: &str, candidate: &str) -> i32 {
+        fuzzy_match(query, candidate).map_or(i32::MIN, |found| found.score)
+    }
+
+    #[test]
+    fn all_characters_must_appear_in_order() {
+        assert!(fuzzy_match("mn", "main").is_some());
+        assert!(fuzzy_match("nm", "main").is_none());
+        assert!(fuzzy_match("mainx", "main").is_none());
+        assert_eq!(fuzzy_match("", "main").map(|found| found.score), Some(0));
+    }
+
+    #[test]
+    fn queries_with_upper_case_letters_match_case() {
+        assert!(fuzzy_match("main", "MAIN.rs").is_some());
+        assert!(fuzzy_match("Main", "main.rs").is_none());
+        assert!(fuzzy_match("Main", "src/Main.java").is_some());
+    }
+
+    #[test]
+    fn consecutive_matches_rank_above_gaps() {
+        assert!(score("main", "xmainx") > score("main", "xmxaxixnx"));
+        assert!(score("abc", "xxabcxxxxx") > score("abc", "xaxbxc"));
+        // Longer gaps cost more
+        assert!(score("ab", "xaxbx") > score("ab", "xaxxxxbx"));
+    }
+
+    #[test]
+    fn matches_at_word_starts_and_in_file_names_rank_higher() {
+        assert!(score("fb", "foo_bar") > score("fb", "xfoxbar"));
+        assert!(score("view", "src/view.rs") > score("view", "view/src.rs"));
+        assert!(score("tf", "src/TextFile.rs") > score("tf", "src/atextfile.rs"));
+    }
+
+    #[test]
+    fn positions_point_at_the_best_matched_characters() {
+        let found = fuzzy_match("mrs", "src/main.rs").unwrap();
+        assert_eq!(found.positions, vec![4, 9, 10]);
+        // Multi-byte characters are reported by their byte index
+        let found = fuzzy_match("ab", "äab").unwrap();
+        assert_eq!(found.positions, vec![2, 3]);
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fs::read_to_string, path::Path};
+
+/// A pattern from a `.gitignore` file.
+struct Rule {
+    /// The directory of the `.gitignore` file, relative to the walked root, with a trailing `/`
+    /// unless it is the root itself
+    base: String,
+    pattern: Vec<char>,
+    /// `!pattern`, which includes paths again that an earlier rule excluded
+    negated: bool,
+    /// `pattern/`, which only applies to directories
+    directory_only: bool,
+    /// Patterns with a `/` before their end are matched against the whole path below `base`,
+    /// others against the name only
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(base: &str, line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        // Trailing spaces are ignored unless escaped
+        let line = if line.ends_with("\\ ") {
+            line
+        } else {
+            line.trim_end_matches(' ')
+        };
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (directory_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            base: base.to_string(),
+            pattern: line.chars().collect(),
+            negated,
+            directory_only,
+            anchored,
+        })
+    }
+
+    /// Returns whether the rule applies to `path`, which is relative to the walked root.
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+        let Some(path) = path.strip_prefix(self.base.as_str()) else {
+            return false;
+        };
+        let text: Vec<char> = if self.anchored {
+            path.chars().collect()
+        } else {
+            path.rsplit('/').next().unwrap_or(path).chars().collect()
+        };
+        glob_matches(&self.pattern, &text)
+    }
+}
+
+/// The ignore rules which apply at some point of a directory walk. Rules of nested `.gitignore`
+/// files are added on the way down and removed again on the way up.
+#[derive(Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Adds the rules of the `.gitignore` file in `dir`, if there is one. `relative_dir` is the
+    /// path of `dir` below the walked root, empty for the root itself. Returns how many rules there
+    /// were before, to be passed to `truncate` when leaving the directory.
+    pub fn enter(&mut self, dir: &Path, relative_dir: &str) -> usize {
+        let rule_count = self.rules.len();
+        if let Ok(contents) = read_to_string(dir.join(".gitignore")) {
+            let base = if relative_dir.is_empty() {
+                String::new()
+            } else {
+                format!("{relative_dir}/")
+            };
+            self.rules
+                .extend(contents.lines().filter_map(|line| Rule::parse(&base, line)));
+        }
+        rule_count
+    }
+
+    pub fn truncate(&mut self, rule_count: usize) {
+        self.rules.truncate(rule_count);
+    }
+
+    /// Returns whether `path`, relative to the walked root, is ignored. As in git, the last
+    /// matching rule decides.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Matches `text` against a glob `pattern` as used by `.gitignore`: `*` and `?` don't match `/`,
+/// `**` matches across directories and `[...]` matches a character class.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directories at all
+            if let ['/', after_slash @ ..] = rest {
+                if glob_matches(after_slash, text) {
+                    return true;
+                }
+            }
+            (0..=text.len()).any(|idx| text.get(idx..).is_some_and(|tail| glob_matches(rest, tail)))
+        }
+        ['*', rest @ ..] => {
+            let segment_len = text.iter().position(|c| *c == '/').unwrap_or(text.len());
+            (0..=segment_len).any(|idx| text.get(idx..).is_some_and(|tail| glob_matches(rest, tail)))
+        }
+        ['?', rest @ ..] => match text {
+            [c, tail @ ..] if *c != '/' => glob_matches(rest, tail),
+            _ => false,
+        },
+        ['[', rest @ ..] => match (text, class_matches(rest, text.first().copied())) {
+            ([_, tail @ ..], Some((true, after_class))) => glob_matches(after_class, tail),
+            // An unterminated class is matched literally
+            ([c, tail @ ..], None) if *c == '[' => glob_matches(rest, tail),
+            _ => false,
+        },
+        ['\\', escaped, rest @ ..] | [escaped, rest @ ..] => match text {
+            [c, tail @ ..] if c == escaped => glob_matches(rest, tail),
+            _ => false,
+        },
+    }
+}
+
+/// Matches `c` against the character class at the start of `pattern`, just after its `[`. Returns
+/// whether it matched and the rest of the pattern, or `None` if the class isn't terminated.
+fn class_matches(pattern: &[char], c: Option<char>) -> Option<(bool, &[char])> {
+    let (negated, mut rest) = match pattern {
+        ['!' | '^', rest @ ..] => (true, rest),
+        _ => (false, pattern),
+    };
+    let mut matched = false;
+    let mut is_first = true;
+    loop {
+        match rest {
+            [']', after @ ..] if !is_first => {
+                let matched = c.is_some_and(|c| c != '/') && matched != negated;
+                return Some((matched, after));
+            }
+            [start, '-', end, after @ ..] if *end != ']' => {
+                matched |= c.is_some_and(|c| (*start..=*end).contains(&c));
+                rest = after;
+            }
+            [member, after @ ..] => {
+                matched |= c == Some(*member);
+                rest = after;
+            }
+            [] => return None,
+        }
+        is_first = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IgnoreRules, Rule};
+
+    /// The rules of a `.gitignore` file holding `lines`, found in `relative_dir` below the root.
+    fn rules(relative_dir: &str, lines: &[&str]) -> IgnoreRules {
+        let base = if relative_dir.is_empty() {
+            String::new()
+        } else {
+            format!("{relative_dir}/")
+        };
+        IgnoreRules {
+            rules: lines.iter().filter_map(|line| Rule::parse(&base, line)).collect(),
+        }
+    }
+
+    #[test]
+    fn unanchored_rules_match_names_in_any_directory() {
+        let rules = rules("", &["*.log", "tmp"]);
+        assert!(rules.is_ignored("debug.log", false));
+        assert!(rules.is_ignored("src/deep/trace.log", false));
+        assert!(rules.is_ignored("src/tmp", true));
+        assert!(!rules.is_ignored("log.txt", false));
+        assert!(!rules.is_ignored("src/tmpfile", false));
+    }
+
+    #[test]
+    fn anchored_rules_match_paths_below_their_gitignore() {
+        let rules = rules("", &["/build", "doc/*.txt"]);
+        assert!(rules.is_ignored("build", true));
+        assert!(!rules.is_ignored("src/build", true));
+        assert!(rules.is_ignored("doc/notes.txt", false));
+        assert!(!rules.is_ignored("doc/old/notes.txt", false));
+        assert!(!rules.is_ignored("src/doc/notes.txt", false));
+
+        let nested = self::rules("sub", &["/generated", "*.bak"]);
+        assert!(nested.is_ignored("sub/generated", false));
+        assert!(!nested.is_ignored("generated", false));
+        assert!(nested.is_ignored("sub/deep/file.bak", false));
+        assert!(!nested.is_ignored("file.bak", false));
+    }
+
+    #[test]
+    fn directory_rules_leave_files_alone() {
+        let rules = rules("", &["out/"]);
+        assert!(rules.is_ignored("out", true));
+        assert!(rules.is_ignored("src/out", true));
+        assert!(!rules.is_ignored("out", false));
+    }
+
+    #[test]
+    fn negated_rules_include_paths_again() {
+        let rules = rules("", &["*.log", "!keep.log", "# a comment", "\\!bang"]);
+        assert!(rules.is_ignored("drop.log", false));
+        assert!(!rules.is_ignored("keep.log", false));
+        assert!(!rules.is_ignored("logs/keep.log", false));
+        assert!(rules.is_ignored("!bang", false));
+        assert!(!rules.is_ignored("# a comment", false));
+
+        // The last matching rule decides
+        let reincluded_first = self::rules("", &["!keep.log", "*.log"]);
+        assert!(reincluded_first.is_ignored("keep.log", false));
+    }
+
+    #[test]
+    fn double_stars_match_across_directories() {
+        let rules = rules("", &["**/cache", "logs/**/*.txt", "vendor/**"]);
+        assert!(rules.is_ignored("cache", true));
+        assert!(rules.is_ignored("a/b/cache", true));
+        assert!(rules.is_ignored("logs/today.txt", false));
+        assert!(rules.is_ignored("logs/2024/may/today.txt", false));
+        assert!(!rules.is_ignored("logs/today.md", false));
+        assert!(rules.is_ignored("vendor/crate/src/lib.rs", false));
+        assert!(!rules.is_ignored("vendor", true));
+    }
+
+    #[test]
+    fn single_stars_and_classes_stay_within_a_directory() {
+        let rules = rules("", &["a*z", "file[0-9].rs", "/src/*.o"]);
+        assert!(rules.is_ignored("abcz", false));
+        assert!(rules.is_ignored("file7.rs", false));
+        assert!(!rules.is_ignored("filex.rs", false));
+        assert!(rules.is_ignored("src/main.o", false));
+        assert!(!rules.is_ignored("src/sub/main.o", false));
+    }
+}
@@ -0,0 +1,90 @@
+use std::str::FromStr;
+
+/// A place to jump to, as entered into the go-to-line prompt.
+/// Line and column numbers are 1-based, as they are reported by compilers.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GotoTarget {
+    /// `42` or `42:7`
+    Line { line: usize, col: Option<usize> },
+    /// `+10`
+    Forward(usize),
+    /// `-10`
+    Backward(usize),
+    /// `%50`
+    Percent(usize),
+}
+
+fn parse_number(value: &str, what: &str) -> Result<usize, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid {what}: `{}`", value.trim()))
+}
+
+/// Parses `line` or `line:col`, as used by the go-to-line prompt and on the command line.
+pub fn parse_line_col(value: &str) -> Result<(usize, Option<usize>), String> {
+    let (line, col) = match value.split_once(':') {
+        Some((line, col)) => (line, Some(col)),
+        None => (value, None),
+    };
+    let line = parse_number(line, "line number")?;
+    let col = col.map(|col| parse_number(col, "column")).transpose()?;
+    Ok((line, col))
+}
+
+impl FromStr for GotoTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(String::from("Please enter a line number"));
+        }
+        if let Some(lines) = value.strip_prefix('+') {
+            return parse_number(lines, "line count").map(Self::Forward);
+        }
+        if let Some(lines) = value.strip_prefix('-') {
+            return parse_number(lines, "line count").map(Self::Backward);
+        }
+        if let Some(percent) = value.strip_prefix('%') {
+            return parse_number(percent, "percentage").map(Self::Percent);
+        }
+        let (line, col) = parse_line_col(value)?;
+        Ok(Self::Line { line, col })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GotoTarget;
+
+    fn parse(value: &str) -> Result<GotoTarget, String> {
+        value.parse()
+    }
+
+    #[test]
+    fn lines_and_columns_are_parsed() {
+        assert_eq!(parse("42"), Ok(GotoTarget::Line { line: 42, col: None }));
+        assert_eq!(parse(" 42:7 "), Ok(GotoTarget::Line { line: 42, col: Some(7) }));
+        assert_eq!(parse("0"), Ok(GotoTarget::Line { line: 0, col: None }));
+    }
+
+    #[test]
+    fn relative_targets_and_percentages_are_parsed() {
+        assert_eq!(parse("+10"), Ok(GotoTarget::Forward(10)));
+        assert_eq!(parse("-3"), Ok(GotoTarget::Backward(3)));
+        assert_eq!(parse("%50"), Ok(GotoTarget::Percent(50)));
+    }
+
+    #[test]
+    fn input_which_is_not_a_number_is_refused() {
+        assert_eq!(parse(""), Err(String::from("Please enter a line number")));
+        assert_eq!(parse("abc"), Err(String::from("Invalid line number: `abc`")));
+        assert_eq!(parse("4:x"), Err(String::from("Invalid column: `x`")));
+        assert_eq!(parse(":4"), Err(String::from("Invalid line number: ``")));
+        assert_eq!(parse("+"), Err(String::from("Invalid line count: ``")));
+        assert_eq!(parse("--3"), Err(String::from("Invalid line count: `-3`")));
+        assert_eq!(parse("%half"), Err(String::from("Invalid percentage: `half`")));
+        assert_eq!(parse("1.5"), Err(String::from("Invalid line number: `1.5`")));
+    }
+}
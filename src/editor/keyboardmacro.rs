@@ -0,0 +1,157 @@
+use std::{collections::HashMap, fs::read_to_string, io::Error, path::PathBuf};
+
+use super::settings::{state_dir, write_state_file};
+
+/// One input the editor processed, as it is replayed by a macro.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MacroStep {
+    /// A command resolved from a key binding, such as `move_down`
+    Command(String),
+    /// A character typed into the buffer or a prompt
+    Insert(char),
+    Paste(String),
+}
+
+impl MacroStep {
+    /// Whether this step records, stops or plays macros, which macros never contain themselves.
+    pub fn is_macro_command(&self) -> bool {
+        let Self::Command(command) = self else {
+            return false;
+        };
+        matches!(command.as_str(), "record_macro" | "stop_macro" | "play_macro")
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Command(_) => "command",
+            Self::Insert(_) => "insert",
+            Self::Paste(_) => "paste",
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Self::Command(command) => command.clone(),
+            Self::Insert(c) => c.to_string(),
+            Self::Paste(text) => text.clone(),
+        }
+    }
+
+    fn parse(kind: &str, value: &str) -> Option<Self> {
+        match kind {
+            "command" => Some(Self::Command(value.to_string())),
+            "insert" => value.chars().next().map(Self::Insert),
+            "paste" => Some(Self::Paste(value.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// A macro which is being recorded.
+pub struct MacroRecording {
+    /// The register the macro is stored in once recording stops
+    pub name: String,
+    pub steps: Vec<MacroStep>,
+}
+
+/// The recorded macros, by the name of their register. The unnamed register holds the macro
+/// recorded without a name and only lasts for the session, the named ones are stored in
+/// `$XDG_STATE_HOME/stack/macros` (or `~/.local/state/stack/macros`) as `name<TAB>kind<TAB>value`
+/// lines, one per step.
+#[derive(Default)]
+pub struct MacroRegisters {
+    registers: HashMap<String, Vec<MacroStep>>,
+}
+
+impl MacroRegisters {
+    fn path() -> Option<PathBuf> {
+        state_dir().map(|dir| dir.join("macros"))
+    }
+
+    /// Loads the named macros, starting out without any if there are none.
+    pub fn load() -> Self {
+        let mut macros = Self::default();
+        let Some(contents) = Self::path().and_then(|path| read_to_string(path).ok()) else {
+            return macros;
+        };
+        for line in contents.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(name), Some(kind), Some(value)) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if let Some(step) = MacroStep::parse(kind, &unescape(value)) {
+                macros.registers.entry(name.to_string()).or_default().push(step);
+            }
+        }
+        macros
+    }
+
+    /// Stores the macro in the register `name` in the macros file. Other editors may have saved
+    /// macros since it was loaded, so it is read again and their macros are kept, and picked up by
+    /// this one.
+    pub fn save(&mut self, name: &str) -> Result<(), Error> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let mut macros = Self::load();
+        macros.set(name, self.get(name).to_vec());
+        let mut names: Vec<&String> = macros.registers.keys().filter(|name| !name.is_empty()).collect();
+        names.sort();
+        let contents: String = names
+            .into_iter()
+            .flat_map(|name| {
+                macros.get(name).iter().map(move |step| {
+                    format!("{name}\t{}\t{}\n", step.kind(), escape(&step.value()))
+                })
+            })
+            .collect();
+        write_state_file(&path, &contents)?;
+        // The unnamed register is never stored, so it is only known to this editor
+        macros.set("", self.get("").to_vec());
+        self.registers = macros.registers;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> &[MacroStep] {
+        self.registers.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set(&mut self, name: &str, steps: Vec<MacroStep>) {
+        self.registers.insert(name.to_string(), steps);
+    }
+}
+
+/// Escapes backslashes, tabs and line breaks, so that pasted text fits on a single line.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
@@ -1,14 +1,22 @@
+use super::Col;
 
 #[derive(Copy, Clone, Debug)]
 pub enum GraphemeWidth {
     Half,
     Full,
+    /// A tab, which extends to the next tab stop.
+    Tab,
 }
-impl From<GraphemeWidth> for usize {
-    fn from(val: GraphemeWidth) -> Self {
-        match val {
-            GraphemeWidth::Half => 1,
-            GraphemeWidth::Full => 2,
+
+impl GraphemeWidth {
+    /// Returns the number of columns the grapheme occupies when rendered starting at `col`.
+    pub fn at_col(self, col: Col, tab_width: usize) -> Col {
+        match self {
+            Self::Half => 1,
+            Self::Full => 2,
+            Self::Tab => tab_width
+                .saturating_sub(col.checked_rem(tab_width).unwrap_or(0))
+                .max(1),
         }
     }
 }
@@ -1,10 +1,13 @@
 use std::{
+    cmp::min,
     fmt::{self, Display},
     ops::{Deref, Range},
 };
 mod graphemewidth;
 mod textfragment;
+mod wordrules;
 use graphemewidth::GraphemeWidth;
+pub use wordrules::WordRules;
 use textfragment::TextFragment;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
@@ -35,8 +38,10 @@ impl Line {
         line_str
             .grapheme_indices(true)
             .map(|(byte_idx, grapheme)| {
-                let (replacement, rendered_width) = Self::get_replacement_character(grapheme)
-                    .map_or_else(
+                let (replacement, rendered_width) = if grapheme == "\t" {
+                    (None, GraphemeWidth::Tab)
+                } else {
+                    Self::get_replacement_character(grapheme).map_or_else(
                         || {
                             let unicode_width = grapheme.width();
                             let rendered_width = match unicode_width {
@@ -46,7 +51,8 @@ impl Line {
                             (None, rendered_width)
                         },
                         |replacement| (Some(replacement), GraphemeWidth::Half),
-                    );
+                    )
+                };
 
                 TextFragment {
                     grapheme: grapheme.to_string(),
@@ -65,8 +71,7 @@ impl Line {
     fn get_replacement_character(for_str: &str) -> Option<char> {
         let width = for_str.width();
         match for_str {
-            " " => None,
-            "\t" => Some(' '),
+            " " | "\t" => None,
             _ if width > 0 && for_str.trim().is_empty() => Some('␣'),
             _ if width == 0 => {
                 let mut chars = for_str.chars();
@@ -81,8 +86,8 @@ impl Line {
         }
     }
 
-    pub fn get_visible_graphemes(&self, range: Range<ColIdx>) -> String {
-        self.get_annotated_visible_substr(range, None, None)
+    pub fn get_visible_graphemes(&self, range: Range<ColIdx>, tab_width: usize) -> String {
+        self.get_annotated_visible_substr(range, None, None, &[], tab_width, false)
             .to_string()
     }
 
@@ -91,47 +96,38 @@ impl Line {
         range: Range<ColIdx>,
         query: Option<&str>,
         selected_match: Option<GraphemeIdx>,
+        highlights: &[(AnnotationType, Range<GraphemeIdx>)],
+        tab_width: usize,
+        show_whitespace: bool,
     ) -> AnnotatedString {
         if range.start >= range.end {
             return AnnotatedString::default();
         }
 
-        let mut result = AnnotatedString::from(&self.string);
-        if let Some(query) = query {
-            if !query.is_empty() {
-                self.find_all(query, 0..self.string.len()).iter().for_each(
-                    |(start_byte_idx, grapheme_idx)| {
-                        if let Some(selected_match) = selected_match {
-                            if *grapheme_idx == selected_match {
-                                result.add_annotation(
-                                    AnnotationType::SelectedMatch,
-                                    *start_byte_idx,
-                                    start_byte_idx.saturating_add(query.len()),
-                                );
-                                return;
-                            }
-                        }
-                        result.add_annotation(
-                            AnnotationType::Match,
-                            *start_byte_idx,
-                            start_byte_idx.saturating_add(query.len()),
-                        );
-                    },
-                );
-            }
-        }
-
-        let mut fragment_start = self.width();
-        for fragment in self.fragments.iter().rev() {
-            let fragment_end = fragment_start;
-            fragment_start = fragment_start.saturating_sub(fragment.rendered_width.into());
+        let mut result = self.annotate(query, selected_match, highlights, show_whitespace);
+        let fragment_cols = self.fragment_cols(tab_width);
+        for (fragment, cols) in self.fragments.iter().zip(fragment_cols).rev() {
+            let Range {
+                start: fragment_start,
+                end: fragment_end,
+            } = cols;
 
             if fragment_start > range.end {
                 continue;
             }
 
             if fragment_start < range.end && fragment_end > range.end {
-                result.replace(fragment.start_byte_idx, self.string.len(), "⋯");
+                // Tabs are blank anyway, so we can show the part of it which fits. The tab may
+                // start before the range as well, in which case its first column is cut off, too.
+                let cut_off = if matches!(fragment.rendered_width, GraphemeWidth::Tab) {
+                    Self::tab_replacement(
+                        range.end.saturating_sub(fragment_start.max(range.start)),
+                        show_whitespace && fragment_start >= range.start,
+                    )
+                } else {
+                    String::from("⋯")
+                };
+                result.replace(fragment.start_byte_idx, self.string.len(), &cut_off);
                 continue;
             } else if fragment_start == range.end {
                 result.replace(fragment.start_byte_idx, self.string.len(), "");
@@ -148,18 +144,34 @@ impl Line {
                 );
                 break;
             } else if fragment_start < range.start && fragment_end > range.start {
+                let cut_off = if matches!(fragment.rendered_width, GraphemeWidth::Tab) {
+                    " ".repeat(fragment_end.saturating_sub(range.start))
+                } else {
+                    String::from("⋯")
+                };
                 result.replace(
                     0,
                     fragment
                         .start_byte_idx
                         .saturating_add(fragment.grapheme.len()),
-                    "⋯",
+                    &cut_off,
                 );
                 break;
             }
 
             if fragment_start >= range.start && fragment_end <= range.end {
-                if let Some(replacement) = fragment.replacement {
+                if matches!(fragment.rendered_width, GraphemeWidth::Tab) {
+                    let start_byte_idx = fragment.start_byte_idx;
+                    let end_byte_idx = start_byte_idx.saturating_add(fragment.grapheme.len());
+                    let spaces = Self::tab_replacement(
+                        fragment_end.saturating_sub(fragment_start),
+                        show_whitespace,
+                    );
+                    result.replace(start_byte_idx, end_byte_idx, &spaces);
+                } else if show_whitespace && fragment.grapheme == " " {
+                    let start_byte_idx = fragment.start_byte_idx;
+                    result.replace(start_byte_idx, start_byte_idx.saturating_add(1), "·");
+                } else if let Some(replacement) = fragment.replacement {
                     let start_byte_idx = fragment.start_byte_idx;
                     let end_byte_idx = start_byte_idx.saturating_add(fragment.grapheme.len());
                     result.replace(start_byte_idx, end_byte_idx, &replacement.to_string());
@@ -167,25 +179,238 @@ impl Line {
             }
         }
 
+        if show_whitespace && (range.start..range.end).contains(&self.width(tab_width)) {
+            result.append("¬", AnnotationType::Whitespace);
+        }
         result
     }
 
+    /// Annotates the whole line with search matches, highlights and, if requested, whitespace.
+    fn annotate(
+        &self,
+        query: Option<&str>,
+        selected_match: Option<GraphemeIdx>,
+        highlights: &[(AnnotationType, Range<GraphemeIdx>)],
+        show_whitespace: bool,
+    ) -> AnnotatedString {
+        let mut result = AnnotatedString::from(&self.string);
+        if show_whitespace {
+            for fragment in &self.fragments {
+                if fragment.grapheme == " " || fragment.grapheme == "\t" {
+                    result.add_annotation(
+                        AnnotationType::Whitespace,
+                        fragment.start_byte_idx,
+                        fragment.start_byte_idx.saturating_add(fragment.grapheme.len()),
+                    );
+                }
+            }
+            result.add_annotation(
+                AnnotationType::TrailingWhitespace,
+                self.string.trim_end_matches([' ', '\t']).len(),
+                self.string.len(),
+            );
+        }
+        if let Some(query) = query {
+            if !query.is_empty() {
+                self.find_all(query, 0..self.string.len()).iter().for_each(
+                    |(start_byte_idx, grapheme_idx)| {
+                        if let Some(selected_match) = selected_match {
+                            if *grapheme_idx == selected_match {
+                                result.add_annotation(
+                                    AnnotationType::SelectedMatch,
+                                    *start_byte_idx,
+                                    start_byte_idx.saturating_add(query.len()),
+                                );
+                                return;
+                            }
+                        }
+                        result.add_annotation(
+                            AnnotationType::Match,
+                            *start_byte_idx,
+                            start_byte_idx.saturating_add(query.len()),
+                        );
+                    },
+                );
+            }
+        }
+        // Later highlights take precedence over earlier ones
+        for (annotation_type, range) in highlights {
+            result.add_annotation(
+                *annotation_type,
+                self.grapheme_idx_to_byte_idx_or_end(range.start),
+                self.grapheme_idx_to_byte_idx_or_end(range.end),
+            );
+        }
+        result
+    }
+
+    /// What is shown for a tab spanning `width` columns, starting at its first column.
+    fn tab_replacement(width: Col, show_whitespace: bool) -> String {
+        if show_whitespace && width > 0 {
+            format!("→{}", " ".repeat(width.saturating_sub(1)))
+        } else {
+            " ".repeat(width)
+        }
+    }
+
+    /// Returns the spaces and tabs at the start of the line.
+    pub fn leading_whitespace(&self) -> &str {
+        let content_len = self.string.trim_start_matches([' ', '\t']).len();
+        self.string
+            .get(..self.string.len().saturating_sub(content_len))
+            .unwrap_or_default()
+    }
+
+    /// Removes spaces and tabs from the end of the line. Returns whether anything was removed.
+    pub fn trim_trailing_whitespace(&mut self) -> bool {
+        let trimmed_len = self.string.trim_end_matches([' ', '\t']).len();
+        if trimmed_len == self.string.len() {
+            return false;
+        }
+        self.string.truncate(trimmed_len);
+        self.rebuild_fragments();
+        true
+    }
+
+    pub fn graphemes(&self) -> impl Iterator<Item = &str> {
+        self.fragments.iter().map(|fragment| fragment.grapheme.as_str())
+    }
+
     pub fn grapheme_count(&self) -> GraphemeIdx {
         self.fragments.len()
     }
-    pub fn width_until(&self, grapheme_idx: GraphemeIdx) -> Col {
+    /// Returns the columns each fragment occupies on screen, in order.
+    fn fragment_cols(&self, tab_width: usize) -> Vec<Range<Col>> {
+        let mut col: Col = 0;
+        self.fragments
+            .iter()
+            .map(|fragment| {
+                let start = col;
+                col = col.saturating_add(fragment.rendered_width.at_col(col, tab_width));
+                start..col
+            })
+            .collect()
+    }
+
+    pub fn width_until(&self, grapheme_idx: GraphemeIdx, tab_width: usize) -> Col {
         self.fragments
             .iter()
             .take(grapheme_idx)
-            .map(|fragment| match fragment.rendered_width {
-                GraphemeWidth::Half => 1,
-                GraphemeWidth::Full => 2,
+            .fold(0, |col: Col, fragment| {
+                col.saturating_add(fragment.rendered_width.at_col(col, tab_width))
+            })
+    }
+
+    /// Returns the index of the grapheme rendered at the given column, or the grapheme count if the column lies behind the line.
+    /// All columns of a wide grapheme or a tab map to that grapheme.
+    pub fn grapheme_idx_at_col(&self, col: Col, tab_width: usize) -> GraphemeIdx {
+        self.fragment_cols(tab_width)
+            .iter()
+            .position(|cols| col < cols.end)
+            .unwrap_or_else(|| self.grapheme_count())
+    }
+
+    /// Returns the range of graphemes forming the word (or the run of whitespace or punctuation) at `grapheme_idx`.
+    pub fn word_range_at(&self, grapheme_idx: GraphemeIdx) -> Range<GraphemeIdx> {
+        let grapheme_idx = min(grapheme_idx, self.grapheme_count().saturating_sub(1));
+        let Some(fragment) = self.fragments.get(grapheme_idx) else {
+            return 0..0;
+        };
+        self.string
+            .split_word_bound_indices()
+            .map(|(start, word)| start..start.saturating_add(word.len()))
+            .find(|bytes| bytes.contains(&fragment.start_byte_idx))
+            .map_or(grapheme_idx..grapheme_idx, |bytes| {
+                let start = self.byte_idx_to_grapheme_idx(bytes.start).unwrap_or(0);
+                let end = self
+                    .byte_idx_to_grapheme_idx(bytes.end)
+                    .unwrap_or_else(|| self.grapheme_count());
+                start..end
             })
-            .sum()
     }
 
-    pub fn width(&self) -> Col {
-        self.width_until(self.grapheme_count())
+    /// Splits the line into runs of graphemes which word-wise movement stops at, following the
+    /// Unicode word boundaries and additionally separating words from punctuation.
+    fn word_ranges(&self, rules: WordRules) -> Vec<Range<GraphemeIdx>> {
+        #[derive(PartialEq, Eq, Clone, Copy)]
+        enum Class {
+            Whitespace,
+            Word,
+            Punctuation,
+        }
+        let is_stop = |class: Class| {
+            class == Class::Word || (class == Class::Punctuation && !rules.skip_punctuation)
+        };
+
+        let mut ranges: Vec<Range<GraphemeIdx>> = Vec::new();
+        let mut previous: Option<(usize, Class)> = None;
+        for (segment_idx, (start_byte_idx, segment)) in
+            self.string.split_word_bound_indices().enumerate()
+        {
+            let Some(first_grapheme_idx) = self.byte_idx_to_grapheme_idx(start_byte_idx) else {
+                continue;
+            };
+            // Apostrophes only belong to a word if they are inside of one, as in "don't".
+            let is_word_segment = segment.chars().any(char::is_alphanumeric);
+            for (offset, grapheme) in segment.graphemes(true).enumerate() {
+                let grapheme_idx = first_grapheme_idx.saturating_add(offset);
+                let first = grapheme.chars().next().unwrap_or(' ');
+                let class = if first.is_whitespace() {
+                    Class::Whitespace
+                } else if first.is_alphanumeric()
+                    || (first == '_' && rules.underscore_in_words)
+                    || (first == '\'' && is_word_segment)
+                {
+                    Class::Word
+                } else {
+                    Class::Punctuation
+                };
+                if is_stop(class) {
+                    // Runs never continue across Unicode word boundaries.
+                    match ranges.last_mut() {
+                        Some(range) if previous == Some((segment_idx, class)) => {
+                            range.end = grapheme_idx.saturating_add(1);
+                        }
+                        _ => ranges.push(grapheme_idx..grapheme_idx.saturating_add(1)),
+                    }
+                }
+                previous = Some((segment_idx, class));
+            }
+        }
+        ranges
+    }
+
+    /// Returns where word-wise movement to the right from `grapheme_idx` ends: the end of the next word,
+    /// or the end of the line if no word follows. Returns `None` if `grapheme_idx` already is at the end.
+    pub fn next_word_end(&self, grapheme_idx: GraphemeIdx, rules: WordRules) -> Option<GraphemeIdx> {
+        if grapheme_idx >= self.grapheme_count() {
+            return None;
+        }
+        self.word_ranges(rules)
+            .iter()
+            .find(|range| range.end > grapheme_idx)
+            .map_or(Some(self.grapheme_count()), |range| Some(range.end))
+    }
+
+    /// Returns where word-wise movement to the left from `grapheme_idx` ends: the start of the previous word,
+    /// or the start of the line if no word precedes. Returns `None` if `grapheme_idx` already is at the start.
+    pub fn previous_word_start(
+        &self,
+        grapheme_idx: GraphemeIdx,
+        rules: WordRules,
+    ) -> Option<GraphemeIdx> {
+        if grapheme_idx == 0 {
+            return None;
+        }
+        self.word_ranges(rules)
+            .iter()
+            .rev()
+            .find(|range| range.start < grapheme_idx)
+            .map_or(Some(0), |range| Some(range.start))
+    }
+
+    pub fn width(&self, tab_width: usize) -> Col {
+        self.width_until(self.grapheme_count(), tab_width)
     }
 
     pub fn insert_char(&mut self, character: char, at: GraphemeIdx) {
@@ -198,10 +423,6 @@ impl Line {
         self.rebuild_fragments();
     }
 
-    pub fn append_char(&mut self, character: char) {
-        self.insert_char(character, self.grapheme_count());
-    }
-
     pub fn delete(&mut self, at: GraphemeIdx) {
         debug_assert!(at <= self.grapheme_count());
         if let Some(fragment) = self.fragments.get(at) {
@@ -214,10 +435,6 @@ impl Line {
         }
     }
 
-    pub fn delete_last(&mut self) {
-        self.delete(self.grapheme_count().saturating_sub(1));
-    }
-
     pub fn append(&mut self, other: &Self) {
         self.string.push_str(&other.string);
         self.rebuild_fragments();
@@ -262,6 +479,12 @@ impl Line {
         )
     }
 
+    fn grapheme_idx_to_byte_idx_or_end(&self, grapheme_idx: GraphemeIdx) -> ByteIdx {
+        self.fragments
+            .get(grapheme_idx)
+            .map_or(self.string.len(), |fragment| fragment.start_byte_idx)
+    }
+
     pub fn search_forward(
         &self,
         query: &str,
@@ -330,3 +553,120 @@ impl Deref for Line {
         &self.string
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::{Line, WordRules};
+
+    fn visible(line: &str, cols: Range<usize>) -> String {
+        Line::from(line).get_visible_graphemes(cols, 4)
+    }
+
+    #[test]
+    fn tabs_extend_to_the_next_tab_stop() {
+        let widths = |line: &str, tab_width| -> Vec<usize> {
+            let line = Line::from(line);
+            (0..=line.grapheme_count())
+                .map(|grapheme_idx| line.width_until(grapheme_idx, tab_width))
+                .collect()
+        };
+        assert_eq!(widths("\tx", 4), [0, 4, 5]);
+        assert_eq!(widths("ab\tc", 4), [0, 1, 2, 4, 5]);
+        assert_eq!(widths("abc\t\t", 4), [0, 1, 2, 3, 4, 8]);
+        assert_eq!(widths("abcd\te", 4), [0, 1, 2, 3, 4, 8, 9]);
+        assert_eq!(widths("ab\tc", 8), [0, 1, 2, 8, 9]);
+        // Wide graphemes count for two columns before the tab stop
+        assert_eq!(widths("中\tx", 4), [0, 2, 4, 5]);
+        assert_eq!(widths("中文x\t", 4), [0, 2, 4, 5, 8]);
+    }
+
+    #[test]
+    fn columns_within_a_tab_belong_to_the_tab() {
+        let line = Line::from("ab\tc");
+        let graphemes: Vec<usize> = (0..6).map(|col| line.grapheme_idx_at_col(col, 4)).collect();
+        assert_eq!(graphemes, [0, 1, 2, 2, 3, 4]);
+    }
+
+    #[test]
+    fn tabs_are_rendered_as_spaces_up_to_the_tab_stop() {
+        assert_eq!(visible("ab\tc", 0..10), "ab  c");
+        assert_eq!(visible("\t\tx", 0..10), "        x");
+        assert_eq!(visible("中\tx", 0..10), "中  x");
+    }
+
+    #[test]
+    fn tabs_cut_by_the_visible_range_show_their_visible_part() {
+        // Starting within the tab at columns 2 to 4
+        assert_eq!(visible("ab\tc", 3..10), " c");
+        assert_eq!(visible("ab\tc", 2..10), "  c");
+        assert_eq!(visible("\tabc", 1..5), "   a");
+        // Ending within it
+        assert_eq!(visible("ab\tc", 0..3), "ab ");
+        assert_eq!(visible("\tabc", 0..2), "  ");
+        // Both
+        assert_eq!(visible("\t\tx", 5..7), "  ");
+        assert_eq!(visible("\tabc", 1..3), "  ");
+    }
+
+    #[test]
+    fn shown_tabs_start_with_an_arrow_unless_it_is_cut_off() {
+        let shown = |line: &str, cols| {
+            Line::from(line)
+                .get_annotated_visible_substr(cols, None, None, &[], 4, true)
+                .to_string()
+        };
+        assert_eq!(shown("a\tx", 0..10), "a→  x¬");
+        assert_eq!(shown("a\tx", 2..10), "  x¬");
+        assert_eq!(shown("a\tx", 1..3), "→ ");
+        assert_eq!(shown("a\tx", 2..3), " ");
+    }
+
+    fn words(line: &str, underscore_in_words: bool, skip_punctuation: bool) -> Vec<Range<usize>> {
+        Line::from(line).word_ranges(WordRules {
+            underscore_in_words,
+            skip_punctuation,
+        })
+    }
+
+    #[test]
+    fn words_and_punctuation_are_separate_stops() {
+        assert_eq!(words("foo_bar.baz(x)", true, false), [0..7, 7..8, 8..11, 11..12, 12..13, 13..14]);
+        assert_eq!(words("  don't 'x'", true, false), [2..7, 8..9, 9..10, 10..11]);
+        assert_eq!(words("", true, false), []);
+        assert_eq!(words(" \t ", true, false), []);
+    }
+
+    #[test]
+    fn underscores_can_separate_words() {
+        assert_eq!(words("foo_bar", false, false), [0..3, 3..4, 4..7]);
+        assert_eq!(words("_x_", false, false), [0..1, 1..2, 2..3]);
+        assert_eq!(words("_x_ y", true, false), [0..3, 4..5]);
+    }
+
+    #[test]
+    fn punctuation_can_be_skipped() {
+        assert_eq!(words("foo.bar(baz);", true, true), [0..3, 4..7, 8..11]);
+        assert_eq!(words("foo_bar::baz", false, true), [0..3, 4..7, 9..12]);
+    }
+
+    #[test]
+    fn non_ascii_letters_belong_to_words() {
+        assert_eq!(words("naïve café", true, false), [0..5, 6..10]);
+        assert_eq!(words("größe_änderung", false, false), [0..5, 5..6, 6..14]);
+        assert_eq!(words("Ωμέγα, ω", true, true), [0..5, 7..8]);
+    }
+
+    #[test]
+    fn the_word_at_a_grapheme_follows_the_unicode_word_boundaries() {
+        let line = Line::from("foo_bar  café.");
+        assert_eq!(line.word_range_at(2), 0..7);
+        assert_eq!(line.word_range_at(7), 7..9);
+        assert_eq!(line.word_range_at(11), 9..13);
+        assert_eq!(line.word_range_at(13), 13..14);
+        // Behind the end of the line, the last word is taken
+        assert_eq!(line.word_range_at(20), 13..14);
+        assert_eq!(Line::from("").word_range_at(0), 0..0);
+    }
+}
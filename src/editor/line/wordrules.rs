@@ -0,0 +1,17 @@
+/// Decides what word-wise movement and deletion treat as a word.
+#[derive(Copy, Clone, Debug)]
+pub struct WordRules {
+    /// Whether `_` belongs to the surrounding word (`foo_bar` is one word) or separates words.
+    pub underscore_in_words: bool,
+    /// Whether runs of punctuation are skipped like whitespace instead of being stopped at like words.
+    pub skip_punctuation: bool,
+}
+
+impl Default for WordRules {
+    fn default() -> Self {
+        Self {
+            underscore_in_words: true,
+            skip_punctuation: false,
+        }
+    }
+}
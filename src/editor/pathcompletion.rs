@@ -0,0 +1,159 @@
+use std::{fs::read_dir, path::PathBuf};
+
+use super::settings::home_dir;
+
+/// Splits `input` into the directory to list, as typed, and the start of the name to complete.
+fn split_input(input: &str) -> (&str, &str) {
+    match input.rfind('/') {
+        Some(idx) => input.split_at(idx.saturating_add(1)),
+        None if input == "~" => (input, ""),
+        None => ("", input),
+    }
+}
+
+/// Turns a path as typed into one which can be accessed, expanding a leading `~`.
+pub fn expand_dir(dir: &str) -> Option<PathBuf> {
+    expand_dir_in(dir, home_dir)
+}
+
+/// Expands `dir` like `expand_dir`, asking `home_dir` for the home directory if it is needed.
+fn expand_dir_in<F: FnOnce() -> Option<PathBuf>>(dir: &str, home_dir: F) -> Option<PathBuf> {
+    if dir.is_empty() {
+        return Some(PathBuf::from("."));
+    }
+    if dir == "~" || dir == "~/" {
+        return home_dir();
+    }
+    if let Some(rest) = dir.strip_prefix("~/") {
+        return home_dir().map(|home| home.join(rest));
+    }
+    Some(PathBuf::from(dir))
+}
+
+/// Returns the paths which `input` could be completed to, sorted, with a trailing `/` on directories.
+/// Hidden entries are only offered once the name being completed starts with a dot.
+pub fn path_candidates(input: &str) -> Vec<String> {
+    let (dir, prefix) = split_input(input);
+    let Some(entries) = expand_dir(dir).and_then(|path| read_dir(path).ok()) else {
+        return Vec::new();
+    };
+    // `~` on its own completes to the home directory
+    let dir = if dir == "~" { "~/" } else { dir };
+    let show_hidden = prefix.starts_with('.');
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !show_hidden) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(format!("{dir}{name}{}", if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// Returns the longest string which all candidates start with.
+pub fn common_prefix(candidates: &[String]) -> String {
+    let Some((first, rest)) = candidates.split_first() else {
+        return String::new();
+    };
+    let mut prefix_len = first.len();
+    for candidate in rest {
+        prefix_len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((idx, a), _)| idx.saturating_add(a.len_utf8()))
+            .min(prefix_len);
+    }
+    first.get(..prefix_len).unwrap_or_default().to_string()
+}
+
+/// Returns the last component of a candidate, keeping the trailing `/` of directories.
+pub fn candidate_name(candidate: &str) -> &str {
+    let trimmed = candidate.strip_suffix('/').unwrap_or(candidate);
+    let start = trimmed.rfind('/').map_or(0, |idx| idx.saturating_add(1));
+    candidate.get(start..).unwrap_or(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::{candidate_name, common_prefix, expand_dir_in, path_candidates, split_input};
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn input_is_split_after_the_last_slash() {
+        assert_eq!(split_input("src/ma"), ("src/", "ma"));
+        assert_eq!(split_input("src/view/"), ("src/view/", ""));
+        assert_eq!(split_input("ma"), ("", "ma"));
+        assert_eq!(split_input(""), ("", ""));
+        assert_eq!(split_input("/"), ("/", ""));
+        assert_eq!(split_input("~"), ("~", ""));
+        assert_eq!(split_input("~/do"), ("~/", "do"));
+        assert_eq!(split_input("~user"), ("", "~user"));
+    }
+
+    #[test]
+    fn a_leading_tilde_expands_to_the_home_directory() {
+        let home = || Some(PathBuf::from("/home/me"));
+        assert_eq!(expand_dir_in("~", home), Some(PathBuf::from("/home/me")));
+        assert_eq!(expand_dir_in("~/", home), Some(PathBuf::from("/home/me")));
+        assert_eq!(expand_dir_in("~/src/", home), Some(PathBuf::from("/home/me/src/")));
+        assert_eq!(expand_dir_in("~/src", || None), None);
+        // Only on its own or before a slash
+        assert_eq!(expand_dir_in("~user/", home), Some(PathBuf::from("~user/")));
+        assert_eq!(expand_dir_in("src/~/", home), Some(PathBuf::from("src/~/")));
+        assert_eq!(expand_dir_in("", home), Some(PathBuf::from(".")));
+    }
+
+    #[test]
+    fn the_common_prefix_ends_at_a_character_boundary() {
+        assert_eq!(common_prefix(&strings(&["src/main.rs", "src/mod.rs"])), "src/m");
+        assert_eq!(common_prefix(&strings(&["café/", "cafè/"])), "caf");
+        assert_eq!(common_prefix(&strings(&["äx", "äy", "äz"])), "ä");
+        assert_eq!(common_prefix(&strings(&["abc", "ab", "abd"])), "ab");
+        assert_eq!(common_prefix(&strings(&["only"])), "only");
+        assert_eq!(common_prefix(&strings(&["a", "b"])), "");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn candidates_are_named_by_their_last_component() {
+        assert_eq!(candidate_name("src/main.rs"), "main.rs");
+        assert_eq!(candidate_name("src/view/"), "view/");
+        assert_eq!(candidate_name("main.rs"), "main.rs");
+        assert_eq!(candidate_name("~/"), "~/");
+        assert_eq!(candidate_name("/"), "/");
+    }
+
+    #[test]
+    fn candidates_are_the_matching_entries_of_the_directory() {
+        let dir = env::temp_dir().join(format!("stack-completion-{}", process::id()));
+        fs::create_dir_all(dir.join("alpine")).unwrap();
+        for file in ["alpha.txt", "beta", ".hidden"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let dir_name = format!("{}/", dir.display());
+        let candidates = |prefix: &str| path_candidates(&format!("{dir_name}{prefix}"));
+        let in_dir = |names: &[&str]| -> Vec<String> {
+            names.iter().map(|name| format!("{dir_name}{name}")).collect()
+        };
+
+        assert_eq!(candidates("al"), in_dir(&["alpha.txt", "alpine/"]));
+        assert_eq!(candidates(""), in_dir(&["alpha.txt", "alpine/", "beta"]));
+        assert_eq!(candidates("."), in_dir(&[".hidden"]));
+        assert_eq!(candidates("x"), in_dir(&[]));
+        assert_eq!(candidates("beta/"), in_dir(&[]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
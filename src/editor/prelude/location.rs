@@ -1,7 +1,23 @@
+use std::cmp::Ordering;
+
 use super::{GraphemeIdx, LineIdx};
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
 pub struct Location {
     pub grapheme_idx: GraphemeIdx,
     pub line_idx: LineIdx,
 }
+
+impl Ord for Location {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.line_idx
+            .cmp(&other.line_idx)
+            .then(self.grapheme_idx.cmp(&other.grapheme_idx))
+    }
+}
+
+impl PartialOrd for Location {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub type GraphemeIdx = usize;
 pub type LineIdx = usize;
 pub type ByteIdx = usize;
@@ -10,6 +12,16 @@ mod size;
 pub use size::Size;
 mod location;
 pub use location::Location;
+mod severity;
+pub use severity::Severity;
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const QUIT_TIMES: u8 = 3;
+pub const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+pub const MOUSE_SCROLL_LINES: usize = 3;
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+pub const MESSAGE_HISTORY_LIMIT: usize = 1000;
+/// How many files the file finder lists at most
+pub const PROJECT_FILE_LIMIT: usize = 100_000;
+/// How long to wait for input before showing more results of a project search
+pub const PROJECT_SEARCH_POLL_INTERVAL: Duration = Duration::from_millis(50);
@@ -1,6 +1,6 @@
 use super::{Col, Row};
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
 pub struct Position {
     pub col: Col,
     pub row: Row,
@@ -0,0 +1,21 @@
+use std::fmt::{self, Display};
+
+/// How important a message shown to the user is.
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Info => "INFO",
+            Self::Warning => "WARNING",
+            Self::Error => "ERROR",
+        };
+        formatter.pad(name)
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    fs::read_dir,
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use super::gitignore::IgnoreRules;
+use super::PROJECT_FILE_LIMIT;
+
+/// Directories which are never worth descending into, whether or not they are ignored.
+const SKIPPED_DIRECTORIES: [&str; 2] = [".git", "target"];
+
+/// Lists the files below `root` as paths relative to it, sorted, leaving out what `.gitignore`
+/// files exclude. Symbolic links to directories are not followed, and the walk stops after
+/// `PROJECT_FILE_LIMIT` files.
+pub fn project_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut rules = IgnoreRules::default();
+    walk(root, "", &mut rules, &mut files);
+    files.sort();
+    files
+}
+
+/// Lists the files below `root` like `project_files`, but on another thread, sending the list
+/// once the walk is done.
+pub fn list_project_files(root: &Path) -> Receiver<Vec<String>> {
+    let (sender, receiver) = channel();
+    let root = root.to_path_buf();
+    thread::spawn(move || {
+        // Nobody may be waiting for the list anymore
+        let _ = sender.send(project_files(&root));
+    });
+    receiver
+}
+
+fn walk(dir: &Path, relative_dir: &str, rules: &mut IgnoreRules, files: &mut Vec<String>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+    let rule_count = rules.enter(dir, relative_dir);
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+    for entry in entries {
+        if files.len() >= PROJECT_FILE_LIMIT {
+            break;
+        }
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = if relative_dir.is_empty() {
+            name.clone()
+        } else {
+            format!("{relative_dir}/{name}")
+        };
+        let is_dir = file_type.is_dir();
+        if rules.is_ignored(&path, is_dir) {
+            continue;
+        }
+        if is_dir {
+            if !SKIPPED_DIRECTORIES.contains(&name.as_str()) {
+                walk(&entry.path(), &path, rules, files);
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    rules.truncate(rule_count);
+}
@@ -0,0 +1,101 @@
+use std::{
+    fs::read,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use super::projectfiles::project_files;
+use super::{GraphemeIdx, Line, Location};
+
+/// A line of a file which contains the query.
+pub struct ProjectMatch {
+    pub path: PathBuf,
+    /// Where the first match on the line starts
+    pub location: Location,
+    /// The line, without its indentation
+    pub text: String,
+    /// Where the matches are within `text`
+    pub matches: Vec<Range<GraphemeIdx>>,
+}
+
+pub enum ProjectSearchEvent {
+    Match(ProjectMatch),
+    Done { files_searched: usize },
+}
+
+/// Searches the files below `root` for `query` on another thread, sending each matching line as it
+/// is found. Files which `.gitignore` excludes and files which don't hold text are skipped. The
+/// search stops early once the receiver is dropped.
+pub fn search_project(root: &Path, query: &str) -> Receiver<ProjectSearchEvent> {
+    let (sender, receiver) = channel();
+    let root = root.to_path_buf();
+    let query = query.to_string();
+    thread::spawn(move || {
+        let mut files_searched: usize = 0;
+        for file in project_files(&root) {
+            let path = root.join(&file);
+            let Some(contents) = read_text(&path) else {
+                continue;
+            };
+            files_searched = files_searched.saturating_add(1);
+            // The paths are shown relative to the root, as they were found
+            if !search_file(Path::new(&file), &contents, &query, &sender) {
+                return;
+            }
+        }
+        let _ = sender.send(ProjectSearchEvent::Done { files_searched });
+    });
+    receiver
+}
+
+/// Reads `path`, unless it looks like a binary file.
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = read(path).ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Sends the lines of `contents` which contain `query`. Returns false if nobody is listening anymore.
+fn search_file(path: &Path, contents: &str, query: &str, sender: &Sender<ProjectSearchEvent>) -> bool {
+    for (line_idx, text) in contents.lines().enumerate() {
+        if !text.contains(query) {
+            continue;
+        }
+        let Some(grapheme_idx) = Line::from(text).search_forward(query, 0) else {
+            continue;
+        };
+        let text = text.trim_start();
+        let result = ProjectMatch {
+            path: path.to_path_buf(),
+            location: Location {
+                grapheme_idx,
+                line_idx,
+            },
+            text: text.to_string(),
+            matches: match_ranges(&Line::from(text), query),
+        };
+        if sender.send(ProjectSearchEvent::Match(result)).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+fn match_ranges(line: &Line, query: &str) -> Vec<Range<GraphemeIdx>> {
+    let query_len = Line::from(query).grapheme_count();
+    let mut ranges = Vec::new();
+    let mut from = 0;
+    while let Some(start) = line.search_forward(query, from) {
+        let end = start.saturating_add(query_len);
+        ranges.push(start..end);
+        from = end;
+        if from >= line.grapheme_count() {
+            break;
+        }
+    }
+    ranges
+}
@@ -0,0 +1,86 @@
+use std::{collections::HashMap, fs::read_to_string, io::Error, path::PathBuf};
+
+use super::settings::{state_dir, write_state_file};
+
+const MAX_ENTRIES_PER_PROMPT: usize = 100;
+
+/// Values previously entered into each kind of prompt, oldest first.
+/// Stored in `$XDG_STATE_HOME/stack/history` (or `~/.local/state/stack/history`) as `kind<TAB>value` lines.
+#[derive(Default)]
+pub struct PromptHistory {
+    entries: HashMap<String, Vec<String>>,
+    /// The entries added since the history was last saved, as `(kind, value)`
+    unsaved: Vec<(String, String)>,
+}
+
+impl PromptHistory {
+    fn path() -> Option<PathBuf> {
+        state_dir().map(|dir| dir.join("history"))
+    }
+
+    /// Loads the history file, starting out empty if there is none.
+    pub fn load() -> Self {
+        let mut history = Self::default();
+        let Some(contents) = Self::path().and_then(|path| read_to_string(path).ok()) else {
+            return history;
+        };
+        for line in contents.lines() {
+            if let Some((kind, value)) = line.split_once('\t') {
+                history.add(kind, value);
+            }
+        }
+        history
+    }
+
+    /// Adds the entries of this session to the history file. Other editors may have saved entries
+    /// since it was loaded, so it is read again and theirs are kept, and picked up by this one.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let mut history = Self::load();
+        for (kind, value) in &self.unsaved {
+            history.add(kind, value);
+        }
+        let mut kinds: Vec<&String> = history.entries.keys().collect();
+        kinds.sort();
+        let contents: String = kinds
+            .into_iter()
+            .flat_map(|kind| {
+                history
+                    .entries(kind)
+                    .iter()
+                    .map(move |value| format!("{kind}\t{value}\n"))
+            })
+            .collect();
+        write_state_file(&path, &contents)?;
+        self.entries = history.entries;
+        self.unsaved.clear();
+        Ok(())
+    }
+
+    pub fn entries(&self, kind: &str) -> &[String] {
+        self.entries.get(kind).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds `value` as the newest entry of `kind`, dropping an earlier identical one.
+    pub fn push(&mut self, kind: &str, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        self.add(kind, value);
+        self.unsaved.push((kind.to_string(), value.to_string()));
+    }
+
+    fn add(&mut self, kind: &str, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        let entries = self.entries.entry(kind.to_string()).or_default();
+        entries.retain(|entry| entry != value);
+        entries.push(value.to_string());
+        if entries.len() > MAX_ENTRIES_PER_PROMPT {
+            entries.remove(0);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    env,
+    fs::{create_dir_all, read_to_string, rename, write},
+    io::Error,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+#[cfg(test)]
+use std::sync::{Mutex, PoisonError};
+
+use super::line::WordRules;
+use super::prelude::{Severity, DEFAULT_TAB_WIDTH, NAME};
+use super::terminal::ColorSupport;
+use super::StatusFormat;
+
+const DEFAULT_STATUS_LEFT: &str = "{name}{readonly} - {lines} {modified}";
+const DEFAULT_STATUS_RIGHT: &str = "{selection}  {filetype} {encoding} {eol}  {line}:{col}  {position}";
+const DEFAULT_THEME: &str = "dark";
+
+/// User configurable behaviour of the editor.
+/// Read from `$XDG_CONFIG_HOME/stack/config` (or `~/.config/stack/config`),
+/// which consists of `key = value` lines. Empty lines and lines starting with `#` are ignored.
+#[derive(Clone, Debug)]
+// clippy::struct_excessive_bools: Settings are mostly independent on/off switches
+#[allow(clippy::struct_excessive_bools)]
+pub struct Settings {
+    pub mouse_capture: bool,
+    pub auto_indent: bool,
+    pub indent_width: usize,
+    pub tab_width: usize,
+    pub expand_tabs: bool,
+    pub underscore_in_words: bool,
+    pub skip_punctuation: bool,
+    pub show_whitespace: bool,
+    pub strip_trailing_whitespace_on_save: bool,
+    pub status_left: StatusFormat,
+    pub status_right: StatusFormat,
+    /// How long messages stay visible, per severity
+    pub info_timeout: Duration,
+    pub warning_timeout: Duration,
+    pub error_timeout: Duration,
+    /// A built-in theme (`dark`, `light`, `solarized-dark`, `solarized-light`) or a theme file
+    pub theme: String,
+    /// Overrides the colour support detected from the environment
+    pub color_support: Option<ColorSupport>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mouse_capture: true,
+            auto_indent: true,
+            indent_width: 4,
+            tab_width: DEFAULT_TAB_WIDTH,
+            expand_tabs: false,
+            underscore_in_words: true,
+            skip_punctuation: false,
+            show_whitespace: false,
+            strip_trailing_whitespace_on_save: false,
+            status_left: DEFAULT_STATUS_LEFT.parse().unwrap_or_default(),
+            status_right: DEFAULT_STATUS_RIGHT.parse().unwrap_or_default(),
+            info_timeout: Duration::from_secs(5),
+            warning_timeout: Duration::from_secs(10),
+            error_timeout: Duration::from_secs(15),
+            theme: String::from(DEFAULT_THEME),
+            color_support: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings from the config file, falling back to the defaults for anything missing.
+    /// Returns the settings along with a description of every line which could not be applied.
+    pub fn load() -> (Self, Vec<String>) {
+        let mut settings = Self::default();
+        let mut errors = Vec::new();
+        let Some(path) = config_dir().map(|dir| dir.join("config")) else {
+            return (settings, errors);
+        };
+        let Ok(contents) = read_to_string(&path) else {
+            return (settings, errors);
+        };
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let result = line
+                .split_once('=')
+                .ok_or_else(|| String::from("expected `key = value`"))
+                .and_then(|(key, value)| settings.apply(key.trim(), value.trim()));
+            if let Err(err) = result {
+                errors.push(format!(
+                    "{}:{}: {err}",
+                    path.display(),
+                    line_idx.saturating_add(1)
+                ));
+            }
+        }
+        (settings, errors)
+    }
+
+    pub const fn word_rules(&self) -> WordRules {
+        WordRules {
+            underscore_in_words: self.underscore_in_words,
+            skip_punctuation: self.skip_punctuation,
+        }
+    }
+
+    pub const fn message_timeout(&self, severity: Severity) -> Duration {
+        match severity {
+            Severity::Info => self.info_timeout,
+            Severity::Warning => self.warning_timeout,
+            Severity::Error => self.error_timeout,
+        }
+    }
+
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "mouse_capture" => self.mouse_capture = parse_bool(value)?,
+            "auto_indent" => self.auto_indent = parse_bool(value)?,
+            "indent_width" => self.indent_width = parse_usize(value)?,
+            "tab_width" => {
+                self.tab_width = parse_usize(value)?;
+                if self.tab_width == 0 {
+                    self.tab_width = DEFAULT_TAB_WIDTH;
+                    return Err(String::from("tab_width must be at least 1"));
+                }
+            }
+            "expand_tabs" => self.expand_tabs = parse_bool(value)?,
+            "underscore_in_words" => self.underscore_in_words = parse_bool(value)?,
+            "skip_punctuation" => self.skip_punctuation = parse_bool(value)?,
+            "show_whitespace" => self.show_whitespace = parse_bool(value)?,
+            "strip_trailing_whitespace_on_save" => {
+                self.strip_trailing_whitespace_on_save = parse_bool(value)?;
+            }
+            "status_left" => self.status_left = value.parse()?,
+            "status_right" => self.status_right = value.parse()?,
+            "info_timeout" => self.info_timeout = parse_seconds(value)?,
+            "warning_timeout" => self.warning_timeout = parse_seconds(value)?,
+            "error_timeout" => self.error_timeout = parse_seconds(value)?,
+            "theme" => self.theme = value.to_string(),
+            "color_support" if value == "auto" => self.color_support = None,
+            "color_support" => self.color_support = Some(value.parse()?),
+            _ => return Err(format!("unknown setting `{key}`")),
+        }
+        Ok(())
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(format!("expected `true` or `false`, got `{value}`")),
+    }
+}
+
+fn parse_usize(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a number, got `{value}`"))
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    parse_usize(value)
+        .and_then(|seconds| u64::try_from(seconds).map_err(|err| err.to_string()))
+        .map(Duration::from_secs)
+}
+
+/// The home directory to use instead of `$HOME`, with the config and state directories in their
+/// default places below it. Tests set it rather than the environment, which the processes they
+/// start read at the same time.
+#[cfg(test)]
+static TEST_HOME: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Uses `home` as the home directory from now on, or the real one again if it is `None`.
+#[cfg(test)]
+pub fn set_test_home(home: Option<PathBuf>) {
+    *TEST_HOME.lock().unwrap_or_else(PoisonError::into_inner) = home;
+}
+
+#[cfg(test)]
+fn test_home() -> Option<PathBuf> {
+    TEST_HOME.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    #[cfg(test)]
+    if let Some(home) = test_home() {
+        return Some(home);
+    }
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The directory named by the environment variable `name`, if it is set and not empty.
+fn xdg_dir(name: &str) -> Option<PathBuf> {
+    #[cfg(test)]
+    if test_home().is_some() {
+        return None;
+    }
+    env::var_os(name)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The directory for data which should persist between sessions, such as prompt history.
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME")
+        .or_else(|| home_dir().map(|home| home.join(".local").join("state")))
+        .map(|dir| dir.join(NAME))
+}
+
+/// Replaces the state file at `path` with `contents`. The contents are written to a temporary
+/// file first, so that an editor reading the file at the same time sees either the old or the new
+/// contents, never a part of them.
+pub fn write_state_file(path: &Path, contents: &str) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", process::id()));
+    write(&temporary, contents)?;
+    rename(&temporary, path)
+}
+
+/// The directory holding the config file, following the XDG base directory conventions.
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME")
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join(NAME))
+}
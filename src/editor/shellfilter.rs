@@ -0,0 +1,135 @@
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// How long to wait between checks whether the command is done or was cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// What a successful filter command printed.
+pub struct FilterOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs `command` with the shell, feeding `input` to its standard input.
+/// Fails with a message describing what went wrong if the command can't be run, exits with an
+/// error, prints something which isn't UTF-8, or is killed because `is_cancelled` returned true
+/// while it was running.
+pub fn run_filter<F: FnMut() -> bool>(
+    command: &str,
+    input: &str,
+    mut is_cancelled: F,
+) -> Result<FilterOutput, String> {
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Could not run `{command}`: {err}"))?;
+
+    // Write and read on other threads, so that a command which prints while it reads can't block on a full pipe
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = input.to_string();
+        // The command is free to exit without reading everything, so errors are of no interest
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        });
+    }
+    let stdout = child.stdout.take().map(read_to_end);
+    let stderr = child.stderr.take().map(read_to_end);
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if is_cancelled() => {
+                let _ = child.kill();
+                let _ = child.wait();
+                // Processes started by the command may still hold the pipes open, so the readers
+                // are left to finish on their own
+                return Err(format!("`{command}` was cancelled"));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(err) => return Err(format!("Could not run `{command}`: {err}")),
+        }
+    };
+    let stdout = stdout.map(join_output).unwrap_or_default();
+    let stderr = stderr.map(join_output).unwrap_or_default();
+
+    let stderr = summarize(&String::from_utf8_lossy(&stderr));
+    if !status.success() {
+        let status = status.code().map_or_else(
+            || String::from("was terminated by a signal"),
+            |code| format!("exited with status {code}"),
+        );
+        return Err(if stderr.is_empty() {
+            format!("`{command}` {status}")
+        } else {
+            format!("`{command}` {status}: {stderr}")
+        });
+    }
+    let stdout = String::from_utf8(stdout)
+        .map_err(|_| format!("`{command}` printed something which isn't UTF-8"))?;
+    Ok(FilterOutput { stdout, stderr })
+}
+
+fn read_to_end<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = pipe.read_to_end(&mut output);
+        output
+    })
+}
+
+fn join_output(reader: JoinHandle<Vec<u8>>) -> Vec<u8> {
+    reader.join().unwrap_or_default()
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Puts the lines of `text` onto one line, so that they fit into the message bar.
+fn summarize(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{run_filter, FilterOutput};
+
+    #[test]
+    fn the_output_of_the_command_replaces_the_input() {
+        let FilterOutput { stdout, stderr } =
+            run_filter("tr a-z A-Z; echo done >&2", "abc\nde\n", || false).unwrap();
+        assert_eq!(stdout, "ABC\nDE\n");
+        assert_eq!(stderr, "done");
+    }
+
+    #[test]
+    fn cancelled_commands_are_killed() {
+        let start = Instant::now();
+        let result = run_filter("sleep 10", "", || true);
+        assert_eq!(result.err(), Some(String::from("`sleep 10` was cancelled")));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{mem, str::FromStr};
+
+use super::DocumentStatus;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Field {
+    Name,
+    ReadOnly,
+    Modified,
+    Lines,
+    Position,
+    Line,
+    Column,
+    VisualColumn,
+    Selection,
+    FileType,
+    Encoding,
+    LineEnding,
+    Mode,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        let field = match name {
+            "name" => Self::Name,
+            "readonly" => Self::ReadOnly,
+            "modified" => Self::Modified,
+            "lines" => Self::Lines,
+            "position" => Self::Position,
+            "line" => Self::Line,
+            "col" => Self::Column,
+            "vcol" => Self::VisualColumn,
+            "selection" => Self::Selection,
+            "filetype" => Self::FileType,
+            "encoding" => Self::Encoding,
+            "eol" => Self::LineEnding,
+            "mode" => Self::Mode,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn render(self, status: &DocumentStatus) -> String {
+        match self {
+            Self::Name => status.file_name.clone(),
+            Self::ReadOnly => status.read_only_indicator_to_string(),
+            Self::Modified => status.modified_indicator_to_string(),
+            Self::Lines => status.line_count_to_string(),
+            Self::Position => status.position_indicator_to_string(),
+            Self::Line => status.current_line_idx.saturating_add(1).to_string(),
+            Self::Column => status.current_grapheme_idx.saturating_add(1).to_string(),
+            Self::VisualColumn => status.current_col.saturating_add(1).to_string(),
+            Self::Selection => status.selection_to_string(),
+            Self::FileType => status.file_type.clone(),
+            Self::Encoding => status.encoding.clone(),
+            Self::LineEnding => status.line_ending.clone(),
+            Self::Mode => status.mode.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Segment {
+    Text(String),
+    Field(Field),
+}
+
+/// The layout of one side of the status bar, such as `{name} - {lines} {modified}`.
+/// Fields are written in braces; `{{` and `}}` stand for literal braces.
+#[derive(Default, Clone, Eq, PartialEq, Debug)]
+pub struct StatusFormat {
+    segments: Vec<Segment>,
+}
+
+impl StatusFormat {
+    pub fn render(&self, status: &DocumentStatus) -> String {
+        let rendered: String = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Field(field) => field.render(status),
+            })
+            .collect();
+        // Empty fields at either end would otherwise leave stray separators
+        rendered.trim().to_string()
+    }
+}
+
+impl FromStr for StatusFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = format.chars();
+        while let Some(character) = chars.next() {
+            match character {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| String::from("unclosed `{`"))?;
+                    let field = Field::from_name(name)
+                        .ok_or_else(|| format!("unknown status field `{{{name}}}`"))?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(field));
+                    chars = rest.chars();
+                }
+                '}' => return Err(String::from("unmatched `}`")),
+                _ => text.push(character),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocumentStatus, StatusFormat};
+
+    fn render(format: &str, status: &DocumentStatus) -> String {
+        format.parse::<StatusFormat>().unwrap().render(status)
+    }
+
+    fn status() -> DocumentStatus {
+        DocumentStatus {
+            total_lines: 120,
+            current_line_idx: 41,
+            current_grapheme_idx: 3,
+            current_col: 9,
+            file_name: String::from("main.rs"),
+            file_type: String::from("Rust"),
+            line_ending: String::from("LF"),
+            is_read_only: true,
+            ..DocumentStatus::default()
+        }
+    }
+
+    #[test]
+    fn fields_are_replaced_by_the_status() {
+        assert_eq!(render("{name}{readonly} - {lines}", &status()), "main.rs [RO] - 120 lines");
+        assert_eq!(render("{line}:{col} ({vcol}) {position}", &status()), "42:4 (10) 42/120");
+        assert_eq!(render("{filetype} {eol}", &status()), "Rust LF");
+        assert_eq!(render("no fields", &status()), "no fields");
+        assert_eq!(render("", &status()), "");
+    }
+
+    #[test]
+    fn empty_fields_at_the_ends_leave_no_blanks() {
+        assert_eq!(render("  {modified} {name} {selection}", &status()), "main.rs");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{{line}}}", &status()), "{42}");
+        assert_eq!(render("}}{{", &status()), "}{");
+    }
+
+    #[test]
+    fn malformed_formats_are_refused() {
+        let parse = |format: &str| format.parse::<StatusFormat>();
+        assert_eq!(parse("{name} {size}"), Err(String::from("unknown status field `{size}`")));
+        assert_eq!(parse("{}"), Err(String::from("unknown status field `{}`")));
+        assert_eq!(parse("{name"), Err(String::from("unclosed `{`")));
+        assert_eq!(parse("{name} {"), Err(String::from("unclosed `{`")));
+        assert_eq!(parse("name}"), Err(String::from("unmatched `}`")));
+    }
+}
@@ -1,39 +1,158 @@
 use crossterm::style::Color;
 
-use crate::editor::annotatedstring::AnnotationType;
-
+/// How a piece of text is drawn. Anything left unset is taken from the style below it, and finally
+/// from the terminal's defaults.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
 pub struct Attribute {
     pub foreground: Option<Color>,
     pub background: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+    pub underline: bool,
+}
+
+impl Attribute {
+    pub const fn new(foreground: Option<Color>, background: Option<Color>) -> Self {
+        Self {
+            foreground,
+            background,
+            bold: false,
+            reverse: false,
+            underline: false,
+        }
+    }
+
+    /// An attribute without colours, made of the given flags.
+    pub const fn flags(bold: bool, reverse: bool, underline: bool) -> Self {
+        Self {
+            foreground: None,
+            background: None,
+            bold,
+            reverse,
+            underline,
+        }
+    }
+
+    pub const fn reversed() -> Self {
+        Self::flags(false, true, false)
+    }
+
+    pub const fn is_plain(&self) -> bool {
+        self.foreground.is_none()
+            && self.background.is_none()
+            && !self.bold
+            && !self.reverse
+            && !self.underline
+    }
 }
 
-impl From<AnnotationType> for Attribute {
-    fn from(annotation_type: AnnotationType) -> Self {
-        match annotation_type {
-            AnnotationType::Match => Self {
-                foreground: Some(Color::Rgb {
-                    r: 255,
-                    g: 255,
-                    b: 255,
-                }),
-                background: Some(Color::Rgb {
-                    r: 100,
-                    g: 100,
-                    b: 100,
-                }),
-            },
-            AnnotationType::SelectedMatch => Self {
-                foreground: Some(Color::Rgb {
-                    r: 255,
-                    g: 255,
-                    b: 255,
-                }),
-                background: Some(Color::Rgb {
-                    r: 255,
-                    g: 251,
-                    b: 0,
-                }),
-            },
+/// Shorthand for a true colour.
+// clippy::unnecessary_wraps: Attributes hold optional colours, so this saves wrapping every colour of a theme
+#[allow(clippy::unnecessary_wraps)]
+pub const fn rgb(r: u8, g: u8, b: u8) -> Option<Color> {
+    Some(Color::Rgb { r, g, b })
+}
+
+/// Parses a style such as `white on #264f78 bold`: an optional foreground colour, optionally
+/// followed by `on` and a background colour, and the flags `bold`, `reverse` and `underline`.
+impl std::str::FromStr for Attribute {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut attribute = Self::default();
+        let mut words = value.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "bold" => attribute.bold = true,
+                "reverse" => attribute.reverse = true,
+                "underline" => attribute.underline = true,
+                "on" => {
+                    let color = words
+                        .next()
+                        .ok_or_else(|| String::from("expected a colour after `on`"))?;
+                    attribute.background = parse_color(color)?;
+                }
+                _ if attribute.foreground.is_some() => {
+                    return Err(format!("unexpected `{word}`"));
+                }
+                _ => attribute.foreground = parse_color(word)?,
+            }
         }
+        Ok(attribute)
     }
-}
\ No newline at end of file
+}
+
+/// Parses `default`, `#rrggbb`, an ANSI colour number or a colour name such as `dark_red`.
+fn parse_color(value: &str) -> Result<Option<Color>, String> {
+    if value == "default" {
+        return Ok(None);
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        let component = |range| {
+            hex.get(range)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+        };
+        // from_str_radix would take a sign, too
+        let is_hex = hex.len() == 6 && hex.bytes().all(|byte| byte.is_ascii_hexdigit());
+        return match (is_hex, component(0..2), component(2..4), component(4..6)) {
+            (true, Some(r), Some(g), Some(b)) => Ok(rgb(r, g, b)),
+            _ => Err(format!("expected a colour like `#1e90ff`, got `{value}`")),
+        };
+    }
+    if let Ok(ansi) = value.parse::<u8>() {
+        return Ok(Some(Color::AnsiValue(ansi)));
+    }
+    Color::try_from(value)
+        .map(Some)
+        .map_err(|()| format!("unknown colour `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rgb, Attribute, Color};
+
+    fn parse(value: &str) -> Result<Attribute, String> {
+        value.parse()
+    }
+
+    #[test]
+    fn colours_are_given_as_hex_numbers_or_names() {
+        assert_eq!(parse("#1e90FF"), Ok(Attribute::new(rgb(30, 144, 255), None)));
+        assert_eq!(parse("dark_red"), Ok(Attribute::new(Some(Color::DarkRed), None)));
+        assert_eq!(parse("208"), Ok(Attribute::new(Some(Color::AnsiValue(208)), None)));
+        assert_eq!(
+            parse("white on #264f78"),
+            Ok(Attribute::new(Some(Color::White), rgb(38, 79, 120)))
+        );
+        assert_eq!(parse("on blue"), Ok(Attribute::new(None, Some(Color::Blue))));
+        assert_eq!(parse("default on default"), Ok(Attribute::default()));
+        assert_eq!(parse(""), Ok(Attribute::default()));
+    }
+
+    #[test]
+    fn modifiers_can_come_anywhere() {
+        assert_eq!(parse("bold underline"), Ok(Attribute::flags(true, false, true)));
+        assert_eq!(
+            parse("reverse red bold on black"),
+            Ok(Attribute {
+                foreground: Some(Color::Red),
+                background: Some(Color::Black),
+                bold: true,
+                reverse: true,
+                underline: false,
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_styles_are_refused() {
+        assert_eq!(parse("#12345"), Err(String::from("expected a colour like `#1e90ff`, got `#12345`")));
+        assert_eq!(parse("#12345g"), Err(String::from("expected a colour like `#1e90ff`, got `#12345g`")));
+        assert_eq!(parse("#+1+1+1"), Err(String::from("expected a colour like `#1e90ff`, got `#+1+1+1`")));
+        assert_eq!(parse("#ééé"), Err(String::from("expected a colour like `#1e90ff`, got `#ééé`")));
+        assert_eq!(parse("pink"), Err(String::from("unknown colour `pink`")));
+        assert_eq!(parse("256"), Err(String::from("unknown colour `256`")));
+        assert_eq!(parse("red on"), Err(String::from("expected a colour after `on`")));
+        assert_eq!(parse("red blue"), Err(String::from("unexpected `blue`")));
+    }
+}
@@ -0,0 +1,178 @@
+use std::ops::Range;
+
+use super::cell::Cell;
+use super::super::prelude::*;
+
+/// The span of a row which has to be reprinted to bring the screen up to date.
+pub struct RowChange {
+    pub cols: Range<Col>,
+    pub clear_to_end: bool,
+}
+
+/// Keeps track of what is currently visible on screen, row by row,
+/// so that only the parts which actually changed need to be sent to the terminal.
+pub struct BackBuffer {
+    rows: Vec<Vec<Cell>>,
+}
+
+impl BackBuffer {
+    pub const fn new() -> Self {
+        Self { rows: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn ensure_rows(&mut self, count: usize) {
+        if self.rows.len() < count {
+            self.rows.resize_with(count, Vec::new);
+        }
+    }
+
+    /// Stores `cells` as the new content of `row` and returns the span which differs from what was drawn before.
+    pub fn update_row(&mut self, row: Row, cells: &[Cell]) -> Option<RowChange> {
+        self.ensure_rows(row.saturating_add(1));
+        let old = self.rows.get(row)?;
+
+        let first_diff = (0..old.len().max(cells.len())).find(|&col| old.get(col) != cells.get(col))?;
+        let last_diff = (first_diff..old.len().max(cells.len()))
+            .rev()
+            .find(|&col| old.get(col) != cells.get(col))
+            .unwrap_or(first_diff);
+
+        // Never start in the middle of a wide grapheme, neither the old nor the new one.
+        let mut start = first_diff;
+        while start > 0
+            && (cells.get(start).is_some_and(Cell::is_continuation)
+                || old.get(start).is_some_and(Cell::is_continuation))
+        {
+            start = start.saturating_sub(1);
+        }
+
+        let clear_to_end = old.len() > cells.len();
+        let mut end = last_diff.saturating_add(1).min(cells.len());
+        while cells.get(end).is_some_and(Cell::is_continuation) {
+            end = end.saturating_add(1);
+        }
+
+        if let Some(stored) = self.rows.get_mut(row) {
+            *stored = cells.to_vec();
+        }
+        Some(RowChange {
+            cols: start..end.max(start),
+            clear_to_end,
+        })
+    }
+
+    /// Moves the content of `rows` up by `by` rows, leaving blank rows at the bottom.
+    pub fn scroll_up(&mut self, rows: Range<Row>, by: usize) {
+        self.ensure_rows(rows.end);
+        if let Some(region) = self.rows.get_mut(rows) {
+            let by = by.min(region.len());
+            region.rotate_left(by);
+            let len = region.len();
+            region
+                .iter_mut()
+                .skip(len.saturating_sub(by))
+                .for_each(Vec::clear);
+        }
+    }
+
+    /// Moves the content of `rows` down by `by` rows, leaving blank rows at the top.
+    pub fn scroll_down(&mut self, rows: Range<Row>, by: usize) {
+        self.ensure_rows(rows.end);
+        if let Some(region) = self.rows.get_mut(rows) {
+            let by = by.min(region.len());
+            region.rotate_right(by);
+            region.iter_mut().take(by).for_each(Vec::clear);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::{BackBuffer, Cell};
+    use crate::editor::annotatedstring::AnnotationType;
+
+    /// Cells holding `graphemes`, where an empty grapheme continues the wide one before it.
+    fn cells(graphemes: &[&str]) -> Vec<Cell> {
+        graphemes
+            .iter()
+            .map(|grapheme| Cell {
+                grapheme: (*grapheme).to_string(),
+                annotation_type: None,
+            })
+            .collect()
+    }
+
+    /// Draws `before`, then `after`, and returns what had to be reprinted for `after`.
+    fn change(before: &[&str], after: &[&str]) -> Option<(Range<usize>, bool)> {
+        let mut back_buffer = BackBuffer::new();
+        back_buffer.update_row(0, &cells(before));
+        back_buffer
+            .update_row(0, &cells(after))
+            .map(|change| (change.cols, change.clear_to_end))
+    }
+
+    #[test]
+    fn unchanged_rows_are_not_reprinted() {
+        assert_eq!(change(&["a", "b"], &["a", "b"]), None);
+        assert_eq!(change(&[], &[]), None);
+    }
+
+    #[test]
+    fn only_the_changed_span_is_reprinted() {
+        assert_eq!(change(&[], &["a", "b", "c"]), Some((0..3, false)));
+        assert_eq!(change(&["a", "b", "c", "d"], &["a", "x", "y", "d"]), Some((1..3, false)));
+        assert_eq!(change(&["a", "b", "c"], &["x", "b", "y"]), Some((0..3, false)));
+
+        let mut back_buffer = BackBuffer::new();
+        back_buffer.update_row(0, &cells(&["a"]));
+        let mut styled = cells(&["a"]);
+        styled[0].annotation_type = Some(AnnotationType::Selection);
+        assert_eq!(back_buffer.update_row(0, &styled).map(|change| change.cols), Some(0..1));
+    }
+
+    #[test]
+    fn longer_rows_print_their_new_tail() {
+        assert_eq!(change(&["a", "b"], &["a", "b", "c", "d"]), Some((2..4, false)));
+        assert_eq!(change(&["a", "b"], &["x", "b", "c"]), Some((0..3, false)));
+    }
+
+    #[test]
+    fn shorter_rows_clear_their_old_tail() {
+        assert_eq!(change(&["a", "b", "c"], &["a", "b"]), Some((2..2, true)));
+        assert_eq!(change(&["a", "b", "c"], &["x"]), Some((0..1, true)));
+        assert_eq!(change(&["a", "b"], &[]), Some((0..0, true)));
+    }
+
+    #[test]
+    fn wide_graphemes_are_reprinted_whole() {
+        // Both halves of a changed wide grapheme
+        assert_eq!(change(&["a", "中", "", "b"], &["a", "文", "", "b"]), Some((1..3, false)));
+        // A wide grapheme replacing a narrow one
+        assert_eq!(change(&["中", "x", "c"], &["中", "", "c"]), Some((0..2, false)));
+        // A narrow grapheme replacing the second half of a wide one
+        assert_eq!(change(&["中", "", "c"], &["中", "y", "c"]), Some((0..2, false)));
+        assert_eq!(change(&["a", "b", "c"], &["a", "中", ""]), Some((1..3, false)));
+    }
+
+    #[test]
+    fn scrolled_rows_keep_their_content() {
+        let mut back_buffer = BackBuffer::new();
+        for (row, grapheme) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            back_buffer.update_row(row, &cells(&[grapheme]));
+        }
+        back_buffer.scroll_up(1..4, 1);
+        assert!(back_buffer.update_row(1, &cells(&["c"])).is_none());
+        assert!(back_buffer.update_row(2, &cells(&["d"])).is_none());
+        assert_eq!(back_buffer.update_row(3, &cells(&["e"])).map(|change| change.cols), Some(0..1));
+        back_buffer.scroll_down(0..4, 2);
+        assert!(back_buffer.update_row(0, &[]).is_none());
+        assert!(back_buffer.update_row(2, &cells(&["a"])).is_none());
+        assert!(back_buffer.update_row(3, &cells(&["c"])).is_none());
+    }
+}
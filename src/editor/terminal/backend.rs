@@ -0,0 +1,33 @@
+use std::io::Error;
+use std::ops::Range;
+
+use super::cell::Cell;
+use super::{ColorSupport, Theme};
+use super::super::prelude::*;
+
+/// Where the output of `Terminal` ends up.
+/// `Terminal` works out what changed on screen, a backend only carries out the drawing.
+pub trait Backend: Send {
+    fn initialize(&mut self) -> Result<(), Error>;
+    fn terminate(&mut self) -> Result<(), Error>;
+    fn size(&self) -> Result<Size, Error>;
+    fn clear_screen(&mut self) -> Result<(), Error>;
+    /// Draws `cells` from `position` on, then clears the rest of the row if `clear_to_end` is set.
+    fn print_cells(&mut self, position: Position, cells: &[Cell], clear_to_end: bool) -> Result<(), Error>;
+    /// Prints `string` at the caret, outside of the rows `Terminal` keeps track of.
+    fn print(&mut self, string: &str) -> Result<(), Error>;
+    /// Moves the content of `rows` up by `by` rows, leaving blank rows at the bottom.
+    fn scroll_up(&mut self, rows: Range<Row>, by: usize) -> Result<(), Error>;
+    /// Moves the content of `rows` down by `by` rows, leaving blank rows at the top.
+    fn scroll_down(&mut self, rows: Range<Row>, by: usize) -> Result<(), Error>;
+    fn move_caret_to(&mut self, position: Position) -> Result<(), Error>;
+    fn hide_caret(&mut self) -> Result<(), Error>;
+    fn show_caret(&mut self) -> Result<(), Error>;
+    fn set_title(&mut self, title: &str) -> Result<(), Error>;
+    fn enable_mouse_capture(&mut self) -> Result<(), Error>;
+    /// Sends everything drawn so far.
+    fn flush(&mut self) -> Result<(), Error>;
+    /// Backends which don't draw colours can ignore the theme.
+    fn set_theme(&mut self, _theme: Theme) {}
+    fn set_color_support(&mut self, _color_support: ColorSupport) {}
+}
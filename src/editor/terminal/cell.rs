@@ -0,0 +1,19 @@
+use crate::editor::annotatedstring::AnnotationType;
+
+/// A single screen column as it was last drawn.
+/// Wide graphemes occupy their own cell plus a continuation cell with an empty grapheme.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Cell {
+    pub grapheme: String,
+    pub annotation_type: Option<AnnotationType>,
+}
+
+impl Cell {
+    pub fn is_continuation(&self) -> bool {
+        self.grapheme.is_empty()
+    }
+
+    pub fn has_same_style(&self, other: &Self) -> bool {
+        self.annotation_type == other.annotation_type
+    }
+}
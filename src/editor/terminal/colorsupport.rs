@@ -0,0 +1,303 @@
+use std::{env, fmt, str::FromStr};
+
+use crossterm::style::Color;
+use crossterm::Command;
+
+/// The 16 basic colours, with the values xterm uses for them by default.
+const BASIC_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+/// The levels of each component in the 6x6x6 colour cube of the 256 colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+const CUBE_START: u8 = 16;
+const GREY_RAMP_START: u8 = 232;
+
+/// How many colours the terminal can show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorSupport {
+    #[default]
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    /// No colours at all, styles fall back to reverse video, underlining and bold text
+    Monochrome,
+}
+
+impl ColorSupport {
+    /// Guesses the colour support from the environment: `NO_COLOR` turns colours off,
+    /// `COLORTERM` announces true colour support, and `TERM` tells the rest.
+    pub fn detect() -> Self {
+        Self::detect_from(|name| env::var(name).unwrap_or_default())
+    }
+
+    /// Guesses the colour support like `detect`, reading the environment variables with `var`.
+    fn detect_from<F: Fn(&str) -> String>(var: F) -> Self {
+        if !var("NO_COLOR").is_empty() {
+            return Self::Monochrome;
+        }
+        if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            return Self::TrueColor;
+        }
+        let term = var("TERM");
+        if term.ends_with("-direct") || term.contains("truecolor") {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else if term.is_empty() || term == "dumb" {
+            Self::Monochrome
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    /// Returns the closest colour the terminal can show, or `None` if it can't show any.
+    pub fn convert(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (Self::Monochrome, _) => None,
+            (Self::Ansi256, Color::Rgb { r, g, b }) => Some(Color::AnsiValue(nearest_ansi256((r, g, b)))),
+            (Self::Ansi16, Color::AnsiValue(value)) => Some(nearest_basic(ansi256_to_rgb(value))),
+            (Self::Ansi16, Color::Rgb { r, g, b }) => Some(nearest_basic((r, g, b))),
+            // Anything else is within what the terminal supports, such as the basic colours
+            (_, _) => Some(color),
+        }
+    }
+}
+
+/// Sets one of the 16 basic colours with the original SGR codes `30`-`37` and `90`-`97` (`40`-`47`
+/// and `100`-`107` for backgrounds), which terminals without the 256 colour palette understand.
+pub struct SetBasicColor {
+    pub color: Color,
+    pub background: bool,
+}
+
+impl Command for SetBasicColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let code: u8 = match basic_index(self.color) {
+            Some(idx) if idx < 8 => idx.saturating_add(30),
+            // The bright colours start at index 8, with code 90
+            Some(idx) => idx.saturating_add(82),
+            // The default colour of the terminal
+            None => 39,
+        };
+        let code = if self.background {
+            code.saturating_add(10)
+        } else {
+            code
+        };
+        write!(f, "\x1b[{code}m")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        if self.background {
+            crossterm::style::SetBackgroundColor(self.color).execute_winapi()
+        } else {
+            crossterm::style::SetForegroundColor(self.color).execute_winapi()
+        }
+    }
+}
+
+/// Parses the `color_support` setting. `auto` is handled by the caller.
+impl FromStr for ColorSupport {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "truecolor" | "24bit" => Ok(Self::TrueColor),
+            "256" => Ok(Self::Ansi256),
+            "16" => Ok(Self::Ansi16),
+            "none" | "monochrome" => Ok(Self::Monochrome),
+            _ => Err(format!(
+                "expected `auto`, `truecolor`, `256`, `16` or `none`, got `{value}`"
+            )),
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    [r1.abs_diff(r2), g1.abs_diff(g2), b1.abs_diff(b2)]
+        .into_iter()
+        .map(|diff| u32::from(diff).saturating_mul(u32::from(diff)))
+        .fold(0, u32::saturating_add)
+}
+
+fn nearest_basic(rgb: (u8, u8, u8)) -> Color {
+    BASIC_COLORS
+        .iter()
+        .min_by_key(|(_, basic)| distance(rgb, *basic))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+/// Returns the index into `BASIC_COLORS` of the basic colour closest to `color`, or `None` if
+/// `color` is the default colour of the terminal.
+fn basic_index(color: Color) -> Option<u8> {
+    let color = match color {
+        Color::Rgb { r, g, b } => nearest_basic((r, g, b)),
+        Color::AnsiValue(value) => nearest_basic(ansi256_to_rgb(value)),
+        color => color,
+    };
+    (0..)
+        .zip(BASIC_COLORS)
+        .find(|(_, (basic, _))| *basic == color)
+        .map(|(idx, _)| idx)
+}
+
+/// Returns the index into `CUBE_LEVELS` of the level closest to `component`.
+fn nearest_cube_level(component: u8) -> u8 {
+    (0..)
+        .zip(CUBE_LEVELS)
+        .min_by_key(|(_, level)| level.abs_diff(component))
+        .map_or(0, |(idx, _)| idx)
+}
+
+/// Finds the closest entry of the 256 colour palette, either in its colour cube or its grey ramp.
+fn nearest_ansi256((r, g, b): (u8, u8, u8)) -> u8 {
+    let (cube_r, cube_g, cube_b) = (nearest_cube_level(r), nearest_cube_level(g), nearest_cube_level(b));
+    let cube_value = CUBE_START
+        .saturating_add(cube_r.saturating_mul(36))
+        .saturating_add(cube_g.saturating_mul(6))
+        .saturating_add(cube_b);
+
+    let average = u16::from(r)
+        .saturating_add(u16::from(g))
+        .saturating_add(u16::from(b))
+        .checked_div(3)
+        .unwrap_or(0);
+    let grey_step = average
+        .saturating_sub(3)
+        .checked_div(10)
+        .and_then(|step| u8::try_from(step).ok())
+        .unwrap_or(u8::MAX)
+        .min(23);
+    let grey_value = GREY_RAMP_START.saturating_add(grey_step);
+
+    if distance((r, g, b), ansi256_to_rgb(grey_value)) < distance((r, g, b), ansi256_to_rgb(cube_value)) {
+        grey_value
+    } else {
+        cube_value
+    }
+}
+
+/// The colour the 256 colour palette has at `value`.
+fn ansi256_to_rgb(value: u8) -> (u8, u8, u8) {
+    if let Some(grey_step) = value.checked_sub(GREY_RAMP_START) {
+        let level = grey_step.saturating_mul(10).saturating_add(8);
+        return (level, level, level);
+    }
+    if let Some(cube_idx) = value.checked_sub(CUBE_START) {
+        let level = |idx: u8| {
+            CUBE_LEVELS
+                .get(usize::from(idx.checked_rem(6).unwrap_or(0)))
+                .copied()
+                .unwrap_or(0)
+        };
+        return (
+            level(cube_idx.checked_div(36).unwrap_or(0)),
+            level(cube_idx.checked_div(6).unwrap_or(0)),
+            level(cube_idx),
+        );
+    }
+    BASIC_COLORS
+        .get(usize::from(value))
+        .map_or((0, 0, 0), |(_, rgb)| *rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ansi256_to_rgb, nearest_ansi256, Color, ColorSupport, Command, SetBasicColor};
+
+    fn detect(vars: &[(&str, &str)]) -> ColorSupport {
+        ColorSupport::detect_from(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (*value).to_string())
+                .unwrap_or_default()
+        })
+    }
+
+    fn sgr(color: Color, background: bool) -> String {
+        let mut code = String::new();
+        SetBasicColor { color, background }.write_ansi(&mut code).unwrap();
+        code
+    }
+
+    #[test]
+    fn colour_support_follows_the_environment() {
+        assert_eq!(detect(&[("NO_COLOR", "1"), ("COLORTERM", "truecolor")]), ColorSupport::Monochrome);
+        assert_eq!(detect(&[("COLORTERM", "truecolor"), ("TERM", "xterm")]), ColorSupport::TrueColor);
+        assert_eq!(detect(&[("COLORTERM", "24bit")]), ColorSupport::TrueColor);
+        assert_eq!(detect(&[("COLORTERM", "yes"), ("TERM", "xterm")]), ColorSupport::Ansi16);
+        assert_eq!(detect(&[("TERM", "xterm-direct")]), ColorSupport::TrueColor);
+        assert_eq!(detect(&[("TERM", "st-truecolor")]), ColorSupport::TrueColor);
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), ColorSupport::Ansi256);
+        assert_eq!(detect(&[("TERM", "linux")]), ColorSupport::Ansi16);
+        assert_eq!(detect(&[("TERM", "dumb")]), ColorSupport::Monochrome);
+        assert_eq!(detect(&[]), ColorSupport::Monochrome);
+    }
+
+    #[test]
+    fn colours_map_to_the_colour_cube() {
+        assert_eq!(nearest_ansi256((0, 0, 0)), 16);
+        assert_eq!(nearest_ansi256((255, 0, 0)), 196);
+        assert_eq!(nearest_ansi256((95, 135, 175)), 67);
+        assert_eq!(nearest_ansi256((255, 255, 255)), 231);
+        // Half way between the first two levels, 0 and 95
+        assert_eq!(nearest_ansi256((47, 0, 255)), 21);
+        assert_eq!(nearest_ansi256((48, 0, 255)), 57);
+        // Dark colours can be closer to the grey ramp than to black
+        assert_eq!(nearest_ansi256((47, 0, 0)), 233);
+    }
+
+    #[test]
+    fn greys_map_to_the_grey_ramp() {
+        assert_eq!(nearest_ansi256((8, 8, 8)), 232);
+        assert_eq!(nearest_ansi256((128, 128, 128)), 244);
+        assert_eq!(nearest_ansi256((238, 238, 238)), 255);
+        // Greys which are on the colour cube stay there
+        assert_eq!(nearest_ansi256((95, 95, 95)), 59);
+        assert_eq!(nearest_ansi256((250, 250, 250)), 231);
+    }
+
+    #[test]
+    fn palette_entries_have_the_xterm_colours() {
+        assert_eq!(ansi256_to_rgb(1), (128, 0, 0));
+        assert_eq!(ansi256_to_rgb(15), (255, 255, 255));
+        assert_eq!(ansi256_to_rgb(16), (0, 0, 0));
+        assert_eq!(ansi256_to_rgb(67), (95, 135, 175));
+        assert_eq!(ansi256_to_rgb(231), (255, 255, 255));
+        assert_eq!(ansi256_to_rgb(232), (8, 8, 8));
+        assert_eq!(ansi256_to_rgb(255), (238, 238, 238));
+        for value in 16..=255 {
+            assert_eq!(nearest_ansi256(ansi256_to_rgb(value)), value);
+        }
+    }
+
+    #[test]
+    fn basic_colours_use_the_original_sgr_codes() {
+        assert_eq!(sgr(Color::Black, false), "\x1b[30m");
+        assert_eq!(sgr(Color::DarkRed, true), "\x1b[41m");
+        assert_eq!(sgr(Color::Grey, false), "\x1b[37m");
+        assert_eq!(sgr(Color::DarkGrey, false), "\x1b[90m");
+        assert_eq!(sgr(Color::White, true), "\x1b[107m");
+        // Other colours are mapped to the closest basic one
+        assert_eq!(sgr(Color::Rgb { r: 250, g: 10, b: 10 }, false), "\x1b[91m");
+        assert_eq!(sgr(Color::AnsiValue(18), true), "\x1b[44m");
+        assert_eq!(sgr(Color::Reset, false), "\x1b[39m");
+        assert_eq!(sgr(Color::Reset, true), "\x1b[49m");
+    }
+}
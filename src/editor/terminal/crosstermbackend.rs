@@ -0,0 +1,237 @@
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
+use crossterm::style::{
+    Attribute::{Bold, Reset, Reverse, Underlined},
+    Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap,
+    EnterAlternateScreen, LeaveAlternateScreen, ScrollDown, ScrollUp, SetTitle,
+};
+use crossterm::{queue, Command};
+use std::io::{stdout, Error, Write};
+use std::ops::Range;
+
+use super::attribute::Attribute;
+use super::backend::Backend;
+use super::cell::Cell;
+use super::colorsupport::SetBasicColor;
+use super::scrollregion::{ResetScrollRegion, SetScrollRegion};
+use super::{ColorSupport, Theme};
+use super::super::annotatedstring::AnnotationType;
+use super::super::prelude::*;
+
+/// Draws to the real terminal on `stdout`, using crossterm.
+#[derive(Default)]
+pub struct CrosstermBackend {
+    theme: Theme,
+    color_support: ColorSupport,
+}
+
+impl CrosstermBackend {
+    /// Runs `action` with the theme to draw with, which is the monochrome one if there are no colours.
+    fn with_theme<T, F: FnOnce(&Theme) -> T>(&self, action: F) -> T {
+        if self.color_support == ColorSupport::Monochrome {
+            return action(&Theme::monochrome());
+        }
+        action(&self.theme)
+    }
+    fn reset_style() -> Result<(), Error> {
+        Self::queue_command(SetAttribute(Reset))?;
+        Self::queue_command(ResetColor)?;
+        Ok(())
+    }
+    /// Applies the style of plain text, with the style of `annotation_type` on top of it.
+    fn set_style(&self, annotation_type: Option<AnnotationType>) -> Result<(), Error> {
+        let (text, annotation) = self.with_theme(|theme| {
+            (theme.text, annotation_type.map(|annotation_type| theme.attribute(annotation_type)))
+        });
+        Self::reset_style()?;
+        self.set_attribute(&text)?;
+        if let Some(annotation) = annotation {
+            self.set_attribute(&annotation)?;
+        }
+        Ok(())
+    }
+    /// Applies `attribute`, converting its colours to ones the terminal can show.
+    fn set_attribute(&self, attribute: &Attribute) -> Result<(), Error> {
+        let color_support = self.color_support;
+        if let Some(color) = attribute
+            .foreground
+            .and_then(|color| color_support.convert(color))
+        {
+            if color_support == ColorSupport::Ansi16 {
+                Self::queue_command(SetBasicColor {
+                    color,
+                    background: false,
+                })?;
+            } else {
+                Self::queue_command(SetForegroundColor(color))?;
+            }
+        }
+        if let Some(color) = attribute
+            .background
+            .and_then(|color| color_support.convert(color))
+        {
+            if color_support == ColorSupport::Ansi16 {
+                Self::queue_command(SetBasicColor {
+                    color,
+                    background: true,
+                })?;
+            } else {
+                Self::queue_command(SetBackgroundColor(color))?;
+            }
+        }
+        if attribute.bold {
+            Self::queue_command(SetAttribute(Bold))?;
+        }
+        if attribute.reverse {
+            Self::queue_command(SetAttribute(Reverse))?;
+        }
+        if attribute.underline {
+            Self::queue_command(SetAttribute(Underlined))?;
+        }
+        Ok(())
+    }
+    // clippy::as_conversions: Truncation to u16 is fine, see the doc on `Terminal`
+    #[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
+    fn scroll_region(&self, rows: &Range<Row>, by: usize, up: bool) -> Result<(), Error> {
+        if rows.is_empty() || by == 0 {
+            return Ok(());
+        }
+        // The exposed rows take on the current background, which has to be the one of plain text,
+        // since they count as blank from now on and their tails are never cleared again.
+        self.set_style(None)?;
+        Self::queue_command(SetScrollRegion {
+            top: rows.start as u16,
+            bottom: rows.end.saturating_sub(1) as u16,
+        })?;
+        if up {
+            Self::queue_command(ScrollUp(by as u16))?;
+        } else {
+            Self::queue_command(ScrollDown(by as u16))?;
+        }
+        Self::queue_command(ResetScrollRegion)?;
+        Self::reset_style()?;
+        Ok(())
+    }
+    fn queue_command<T: Command>(command: T) -> Result<(), Error> {
+        queue!(stdout(), command)?;
+        Ok(())
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn initialize(&mut self) -> Result<(), Error> {
+        enable_raw_mode()?;
+        Self::queue_command(EnterAlternateScreen)?;
+        Self::queue_command(EnableBracketedPaste)?;
+        Self::queue_command(DisableLineWrap)?;
+        self.clear_screen()?;
+        self.flush()?;
+        Ok(())
+    }
+    fn terminate(&mut self) -> Result<(), Error> {
+        Self::queue_command(DisableMouseCapture)?;
+        Self::queue_command(DisableBracketedPaste)?;
+        Self::queue_command(LeaveAlternateScreen)?;
+        Self::queue_command(EnableLineWrap)?;
+        self.show_caret()?;
+        self.flush()?;
+        disable_raw_mode()?;
+        Ok(())
+    }
+    /// Edge Case for systems with `usize` < `u16`:
+    /// * A `Size` representing the terminal size. Any coordinate `z` truncated to `usize` if `usize` < `z` < `u16`
+    fn size(&self) -> Result<Size, Error> {
+        let (width_u16, height_u16) = size()?;
+        // clippy::as_conversions: See doc above
+        #[allow(clippy::as_conversions)]
+        let height = height_u16 as usize;
+        // clippy::as_conversions: See doc above
+        #[allow(clippy::as_conversions)]
+        let width = width_u16 as usize;
+        Ok(Size { height, width })
+    }
+    fn clear_screen(&mut self) -> Result<(), Error> {
+        // Clear with the background of the theme
+        self.set_style(None)?;
+        Self::queue_command(Clear(ClearType::All))?;
+        Self::reset_style()?;
+        Ok(())
+    }
+    fn print_cells(&mut self, position: Position, cells: &[Cell], clear_to_end: bool) -> Result<(), Error> {
+        self.move_caret_to(position)?;
+        // The terminal is always left without any styling after printing, so we only need to emit
+        // style changes, unless plain text has a style of its own.
+        let plain = Cell::default();
+        let text_is_plain = self.with_theme(|theme| theme.text.is_plain());
+        let mut current_style = text_is_plain.then_some(&plain);
+        for cell in cells {
+            if !current_style.is_some_and(|current| current.has_same_style(cell)) {
+                self.set_style(cell.annotation_type)?;
+                current_style = Some(cell);
+            }
+            if !cell.is_continuation() {
+                self.print(&cell.grapheme)?;
+            }
+        }
+        if clear_to_end {
+            // Clear with the background of plain text
+            if !current_style.is_some_and(|current| current.has_same_style(&plain)) {
+                self.set_style(None)?;
+                current_style = Some(&plain);
+            }
+            Self::queue_command(Clear(ClearType::UntilNewLine))?;
+        }
+        if !text_is_plain || !current_style.is_some_and(|current| current.has_same_style(&plain)) {
+            Self::reset_style()?;
+        }
+        Ok(())
+    }
+    fn print(&mut self, string: &str) -> Result<(), Error> {
+        Self::queue_command(Print(string))?;
+        Ok(())
+    }
+    fn scroll_up(&mut self, rows: Range<Row>, by: usize) -> Result<(), Error> {
+        self.scroll_region(&rows, by, true)
+    }
+    fn scroll_down(&mut self, rows: Range<Row>, by: usize) -> Result<(), Error> {
+        self.scroll_region(&rows, by, false)
+    }
+    /// Moves the caret to the given Position, truncated to `u16::MAX` if bigger.
+    fn move_caret_to(&mut self, position: Position) -> Result<(), Error> {
+        // clippy::as_conversions: See doc above
+        #[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
+        Self::queue_command(MoveTo(position.col as u16, position.row as u16))?;
+        Ok(())
+    }
+    fn hide_caret(&mut self) -> Result<(), Error> {
+        Self::queue_command(Hide)?;
+        Ok(())
+    }
+    fn show_caret(&mut self) -> Result<(), Error> {
+        Self::queue_command(Show)?;
+        Ok(())
+    }
+    fn set_title(&mut self, title: &str) -> Result<(), Error> {
+        Self::queue_command(SetTitle(title))?;
+        Ok(())
+    }
+    fn enable_mouse_capture(&mut self) -> Result<(), Error> {
+        Self::queue_command(EnableMouseCapture)?;
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        stdout().flush()?;
+        Ok(())
+    }
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+    fn set_color_support(&mut self, color_support: ColorSupport) {
+        self.color_support = color_support;
+    }
+}
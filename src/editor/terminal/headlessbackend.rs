@@ -0,0 +1,204 @@
+use std::io::Error;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use super::backend::Backend;
+use super::cell::Cell;
+use super::super::annotatedstring::AnnotationType;
+use super::super::prelude::*;
+
+/// What a `HeadlessBackend` has drawn so far.
+#[derive(Default)]
+struct Screen {
+    size: Size,
+    rows: Vec<Vec<Cell>>,
+    caret: Position,
+    caret_visible: bool,
+    title: String,
+}
+
+impl Screen {
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![blank_cell(); self.size.width]
+    }
+}
+
+/// Keeps what would have been drawn in a grid of cells in memory instead of showing it,
+/// so that the editor can be driven and inspected without a terminal.
+/// Clones share the same grid, so one can be handed to `Terminal` while the other is inspected.
+#[derive(Clone, Default)]
+pub struct HeadlessBackend {
+    screen: Arc<Mutex<Screen>>,
+}
+
+impl HeadlessBackend {
+    pub fn new(size: Size) -> Self {
+        let backend = Self::default();
+        backend.with_screen(|screen| {
+            screen.size = size;
+            screen.rows = vec![screen.blank_row(); size.height];
+        });
+        backend
+    }
+
+    /// The text on screen, one line per row, without trailing blanks.
+    pub fn text(&self) -> String {
+        self.render(|cell| cell.grapheme.clone())
+    }
+
+    /// The styles on screen in the same layout as `text`, with one character per cell:
+    /// a blank for plain text and a letter for each annotation type, see `style_char`.
+    pub fn styles(&self) -> String {
+        self.render(|cell| String::from(cell.annotation_type.map_or(' ', style_char)))
+    }
+
+    /// Where the caret is, if it is visible.
+    pub fn caret(&self) -> Option<Position> {
+        self.with_screen(|screen| screen.caret_visible.then_some(screen.caret))
+    }
+
+    pub fn title(&self) -> String {
+        self.with_screen(|screen| screen.title.clone())
+    }
+
+    fn render<F: Fn(&Cell) -> String>(&self, cell_to_string: F) -> String {
+        self.with_screen(|screen| {
+            screen
+                .rows
+                .iter()
+                .map(|row| {
+                    let line: String = row.iter().map(&cell_to_string).collect();
+                    line.trim_end().to_string()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    fn with_screen<T, F: FnOnce(&mut Screen) -> T>(&self, action: F) -> T {
+        let mut screen = self.screen.lock().unwrap_or_else(PoisonError::into_inner);
+        action(&mut screen)
+    }
+
+    fn blank_rows(&self, rows: Range<Row>) {
+        self.with_screen(|screen| {
+            let blank = screen.blank_row();
+            if let Some(region) = screen.rows.get_mut(rows) {
+                region.fill(blank);
+            }
+        });
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn initialize(&mut self) -> Result<(), Error> {
+        self.clear_screen()
+    }
+    fn terminate(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn size(&self) -> Result<Size, Error> {
+        Ok(self.with_screen(|screen| screen.size))
+    }
+    fn clear_screen(&mut self) -> Result<(), Error> {
+        let height = self.with_screen(|screen| screen.size.height);
+        self.blank_rows(0..height);
+        Ok(())
+    }
+    fn print_cells(&mut self, position: Position, cells: &[Cell], clear_to_end: bool) -> Result<(), Error> {
+        self.with_screen(|screen| {
+            let Some(row) = screen.rows.get_mut(position.row) else {
+                return;
+            };
+            // Anything past the right edge is cut off, as line wrapping is disabled
+            for (target, cell) in row.iter_mut().skip(position.col).zip(cells) {
+                target.clone_from(cell);
+            }
+            if clear_to_end {
+                row.iter_mut()
+                    .skip(position.col.saturating_add(cells.len()))
+                    .for_each(|cell| *cell = blank_cell());
+            }
+        });
+        Ok(())
+    }
+    fn print(&mut self, _string: &str) -> Result<(), Error> {
+        // Only the rows drawn by `Terminal` are recorded
+        Ok(())
+    }
+    fn scroll_up(&mut self, rows: Range<Row>, by: usize) -> Result<(), Error> {
+        self.with_screen(|screen| {
+            let blank = screen.blank_row();
+            if let Some(region) = screen.rows.get_mut(rows) {
+                let by = by.min(region.len());
+                region.rotate_left(by);
+                let len = region.len();
+                region
+                    .iter_mut()
+                    .skip(len.saturating_sub(by))
+                    .for_each(|row| row.clone_from(&blank));
+            }
+        });
+        Ok(())
+    }
+    fn scroll_down(&mut self, rows: Range<Row>, by: usize) -> Result<(), Error> {
+        self.with_screen(|screen| {
+            let blank = screen.blank_row();
+            if let Some(region) = screen.rows.get_mut(rows) {
+                let by = by.min(region.len());
+                region.rotate_right(by);
+                region
+                    .iter_mut()
+                    .take(by)
+                    .for_each(|row| row.clone_from(&blank));
+            }
+        });
+        Ok(())
+    }
+    fn move_caret_to(&mut self, position: Position) -> Result<(), Error> {
+        self.with_screen(|screen| screen.caret = position);
+        Ok(())
+    }
+    fn hide_caret(&mut self) -> Result<(), Error> {
+        self.with_screen(|screen| screen.caret_visible = false);
+        Ok(())
+    }
+    fn show_caret(&mut self) -> Result<(), Error> {
+        self.with_screen(|screen| screen.caret_visible = true);
+        Ok(())
+    }
+    fn set_title(&mut self, title: &str) -> Result<(), Error> {
+        self.with_screen(|screen| screen.title = title.to_string());
+        Ok(())
+    }
+    fn enable_mouse_capture(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+fn blank_cell() -> Cell {
+    Cell {
+        grapheme: String::from(" "),
+        annotation_type: None,
+    }
+}
+
+/// The character `HeadlessBackend::styles` shows for cells annotated with `annotation_type`.
+const fn style_char(annotation_type: AnnotationType) -> char {
+    match annotation_type {
+        AnnotationType::Match => 'm',
+        AnnotationType::SelectedMatch => 'M',
+        AnnotationType::Selection => 's',
+        AnnotationType::MatchingBracket => 'b',
+        AnnotationType::Whitespace => 'w',
+        AnnotationType::TrailingWhitespace => 't',
+        AnnotationType::StatusBar => '=',
+        AnnotationType::Gutter => 'g',
+        AnnotationType::InfoMessage => 'i',
+        AnnotationType::WarningMessage => 'W',
+        AnnotationType::ErrorMessage => 'E',
+    }
+}
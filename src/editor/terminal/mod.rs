@@ -1,19 +1,36 @@
 mod attribute;
-use attribute::Attribute;
-use crossterm::cursor::{Hide, MoveTo, Show};
-use crossterm::style::{
-    Attribute::{Reset, Reverse},
-    Print, ResetColor, SetBackgroundColor, SetForegroundColor,
-};
-use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap,
-    EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
-};
-use crossterm::{queue, Command};
-use std::io::{stdout, Error, Write};
+mod backbuffer;
+use backbuffer::BackBuffer;
+mod backend;
+pub use backend::Backend;
+mod cell;
+use cell::Cell;
+mod colorsupport;
+pub use colorsupport::ColorSupport;
+mod crosstermbackend;
+use crosstermbackend::CrosstermBackend;
+#[cfg(test)]
+mod headlessbackend;
+#[cfg(test)]
+pub use headlessbackend::HeadlessBackend;
+mod scrollregion;
+mod theme;
+pub use theme::Theme;
+use std::io::Error;
+use std::ops::Range;
+use std::sync::{Mutex, PoisonError};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
+use super::annotatedstring::AnnotationType;
 use super::AnnotatedString;
-use super::{Position, Size};
+use super::{Position, Row, Size};
+
+/// What we believe is currently on screen, used to only redraw what changed.
+static BACK_BUFFER: Mutex<BackBuffer> = Mutex::new(BackBuffer::new());
+/// Where everything is drawn, see `Terminal::set_backend`. Drawing goes to the real terminal until
+/// another backend is set.
+static BACKEND: Mutex<Option<Box<dyn Backend>>> = Mutex::new(None);
 
 /// Represents the Terminal.
 /// Edge Case for platforms where `usize` < `u16`:
@@ -25,133 +42,131 @@ pub struct Terminal;
 
 impl Terminal {
     pub fn terminate() -> Result<(), Error> {
-        Self::leave_alternate_screen()?;
-        Self::enable_line_wrap()?;
-        Self::show_caret()?;
-        Self::execute()?;
-        disable_raw_mode()?;
-        Ok(())
+        Self::with_backend(|backend| backend.terminate())
     }
     pub fn initialize() -> Result<(), Error> {
-        enable_raw_mode()?;
-        Self::enter_alternate_screen()?;
-        Self::disable_line_wrap()?;
-        Self::clear_screen()?;
-        Self::execute()?;
+        Self::with_backend(|backend| backend.initialize())?;
+        Self::with_back_buffer(BackBuffer::clear);
         Ok(())
     }
-    pub fn clear_screen() -> Result<(), Error> {
-        Self::queue_command(Clear(ClearType::All))?;
-        Ok(())
+    /// Draws to `backend` from now on. Everything drawn before has to be drawn again.
+    #[cfg(test)]
+    pub fn set_backend(backend: Box<dyn Backend>) {
+        *BACKEND.lock().unwrap_or_else(PoisonError::into_inner) = Some(backend);
+        Self::with_back_buffer(BackBuffer::clear);
     }
-    pub fn clear_line() -> Result<(), Error> {
-        Self::queue_command(Clear(ClearType::CurrentLine))?;
+    /// Switches to `theme`. Everything drawn before has to be drawn again to take it on.
+    pub fn set_theme(theme: Theme) {
+        Self::with_backend(|backend| backend.set_theme(theme));
+        Self::with_back_buffer(BackBuffer::clear);
+    }
+    /// Draws with the colours `color_support` allows. Everything drawn before has to be drawn again.
+    pub fn set_color_support(color_support: ColorSupport) {
+        Self::with_backend(|backend| backend.set_color_support(color_support));
+        Self::with_back_buffer(BackBuffer::clear);
+    }
+    pub fn clear_screen() -> Result<(), Error> {
+        Self::with_backend(|backend| backend.clear_screen())?;
+        Self::with_back_buffer(BackBuffer::clear);
         Ok(())
     }
     /// Moves the caret to the given Position.
     /// # Arguments
     /// * `Position` - the  `Position`to move the caret to. Will be truncated to `u16::MAX` if bigger.
     pub fn move_caret_to(position: Position) -> Result<(), Error> {
-        // clippy::as_conversions: See doc above
-        #[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
-        Self::queue_command(MoveTo(position.col as u16, position.row as u16))?;
-        Ok(())
-    }
-    pub fn enter_alternate_screen() -> Result<(), Error> {
-        Self::queue_command(EnterAlternateScreen)?;
-        Ok(())
-    }
-    pub fn leave_alternate_screen() -> Result<(), Error> {
-        Self::queue_command(LeaveAlternateScreen)?;
-        Ok(())
+        Self::with_backend(|backend| backend.move_caret_to(position))
     }
     pub fn hide_caret() -> Result<(), Error> {
-        Self::queue_command(Hide)?;
-        Ok(())
+        Self::with_backend(|backend| backend.hide_caret())
     }
     pub fn show_caret() -> Result<(), Error> {
-        Self::queue_command(Show)?;
-        Ok(())
-    }
-    pub fn disable_line_wrap() -> Result<(), Error> {
-        Self::queue_command(DisableLineWrap)?;
-        Ok(())
+        Self::with_backend(|backend| backend.show_caret())
     }
-    pub fn enable_line_wrap() -> Result<(), Error> {
-        Self::queue_command(EnableLineWrap)?;
-        Ok(())
+    pub fn enable_mouse_capture() -> Result<(), Error> {
+        Self::with_backend(|backend| backend.enable_mouse_capture())
     }
     pub fn set_title(title: &str) -> Result<(), Error> {
-        Self::queue_command(SetTitle(title))?;
-        Ok(())
+        Self::with_backend(|backend| backend.set_title(title))
     }
     pub fn print(string: &str) -> Result<(), Error> {
-        Self::queue_command(Print(string))?;
-        Ok(())
+        Self::with_backend(|backend| backend.print(string))
     }
     pub fn print_row(row: usize, line_text: &str) -> Result<(), Error> {
-        Self::move_caret_to(Position { row, col: 0 })?;
-        Self::clear_line()?;
-        Self::print(line_text)?;
-        Ok(())
+        Self::print_cells(row, &Self::str_to_cells(line_text, None))
     }
     pub fn print_annotated_row(
         row: usize,
         annotated_string: &AnnotatedString,
     ) -> Result<(), Error> {
-        Self::move_caret_to(Position { row, col: 0 })?;
-        Self::clear_line()?;
-        annotated_string
+        let cells = annotated_string
             .into_iter()
-            .try_for_each(|part| -> Result<(), Error> {
-                if let Some(annotation_type) = part.annotation_type {
-                    let attribute: Attribute = annotation_type.into();
-                    Self::set_attribute(&attribute)?;
-                }
-
-                Self::print(part.string)?;
-                Self::reset_color()?;
-                Ok(())
-            })?;
+            .flat_map(|part| Self::str_to_cells(part.string, part.annotation_type))
+            .collect::<Vec<_>>();
+        Self::print_cells(row, &cells)
+    }
+    /// Scrolls the content of `rows` up by `by` rows using a terminal scroll region,
+    /// so that only the newly exposed rows at the bottom need to be printed.
+    pub fn scroll_up(rows: Range<Row>, by: usize) -> Result<(), Error> {
+        if rows.is_empty() || by == 0 {
+            return Ok(());
+        }
+        Self::with_backend(|backend| backend.scroll_up(rows.clone(), by))?;
+        Self::with_back_buffer(|back_buffer| back_buffer.scroll_up(rows, by));
         Ok(())
     }
-    fn set_attribute(attribute: &Attribute) -> Result<(), Error> {
-        if let Some(foreground_color) = attribute.foreground {
-            Self::queue_command(SetForegroundColor(foreground_color))?;
+    /// Scrolls the content of `rows` down by `by` rows using a terminal scroll region,
+    /// so that only the newly exposed rows at the top need to be printed.
+    pub fn scroll_down(rows: Range<Row>, by: usize) -> Result<(), Error> {
+        if rows.is_empty() || by == 0 {
+            return Ok(());
         }
-        if let Some(background_color) = attribute.background {
-            Self::queue_command(SetBackgroundColor(background_color))?;
+        Self::with_backend(|backend| backend.scroll_down(rows.clone(), by))?;
+        Self::with_back_buffer(|back_buffer| back_buffer.scroll_down(rows, by));
+        Ok(())
+    }
+    fn str_to_cells(string: &str, annotation_type: Option<AnnotationType>) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        for grapheme in string.graphemes(true) {
+            cells.push(Cell {
+                grapheme: grapheme.to_string(),
+                annotation_type,
+            });
+            if grapheme.width() > 1 {
+                cells.push(Cell {
+                    grapheme: String::new(),
+                    annotation_type,
+                });
+            }
         }
-        Ok(())
-    }
-    fn reset_color() -> Result<(), Error> {
-        Self::queue_command(ResetColor)?;
-        Ok(())
-    }
-    pub fn print_inverted_row(row: usize, line_text: &str) -> Result<(), Error> {
-        let width = Self::size()?.width;
-        Self::print_row(row, &format!("{Reverse}{line_text:width$.width$}{Reset}"))
+        cells
+    }
+    /// Prints only the part of `cells` which differs from what was previously drawn at `row`.
+    fn print_cells(row: usize, cells: &[Cell]) -> Result<(), Error> {
+        let Some(change) = Self::with_back_buffer(|back_buffer| back_buffer.update_row(row, cells))
+        else {
+            return Ok(());
+        };
+        let position = Position {
+            row,
+            col: change.cols.start,
+        };
+        let changed = cells.get(change.cols).unwrap_or_default();
+        Self::with_backend(|backend| backend.print_cells(position, changed, change.clear_to_end))
+    }
+    fn with_back_buffer<T, F: FnOnce(&mut BackBuffer) -> T>(action: F) -> T {
+        let mut back_buffer = BACK_BUFFER.lock().unwrap_or_else(PoisonError::into_inner);
+        action(&mut back_buffer)
+    }
+    fn with_backend<T, F: FnOnce(&mut dyn Backend) -> T>(action: F) -> T {
+        let mut backend = BACKEND.lock().unwrap_or_else(PoisonError::into_inner);
+        let backend = backend.get_or_insert_with(|| Box::new(CrosstermBackend::default()));
+        action(backend.as_mut())
     }
     /// Returns the current size of this Terminal.
-    /// Edge Case for systems with `usize` < `u16`:
-    /// * A `Size` representing the terminal size. Any coordinate `z` truncated to `usize` if `usize` < `z` < `u16`
     pub fn size() -> Result<Size, Error> {
-        let (width_u16, height_u16) = size()?;
-        // clippy::as_conversions: See doc above
-        #[allow(clippy::as_conversions)]
-        let height = height_u16 as usize;
-        // clippy::as_conversions: See doc above
-        #[allow(clippy::as_conversions)]
-        let width = width_u16 as usize;
-        Ok(Size { height, width })
+        Self::with_backend(|backend| backend.size())
     }
     pub fn execute() -> Result<(), Error> {
-        stdout().flush()?;
-        Ok(())
-    }
-
-    fn queue_command<T: Command>(command: T) -> Result<(), Error> {
-        queue!(stdout(), command)?;
-        Ok(())
+        Self::with_backend(|backend| backend.flush())
     }
 }
@@ -0,0 +1,42 @@
+use crossterm::Command;
+use std::fmt;
+
+/// Restricts scrolling to the rows `top..=bottom` (both zero-based).
+pub struct SetScrollRegion {
+    pub top: u16,
+    pub bottom: u16,
+}
+
+impl Command for SetScrollRegion {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            f,
+            "\x1b[{};{}r",
+            self.top.saturating_add(1),
+            self.bottom.saturating_add(1)
+        )
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Err(std::io::Error::other(
+            "Scroll regions are not supported by the legacy Windows console",
+        ))
+    }
+}
+
+/// Resets the scroll region to the whole screen.
+pub struct ResetScrollRegion;
+
+impl Command for ResetScrollRegion {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[r")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Err(std::io::Error::other(
+            "Scroll regions are not supported by the legacy Windows console",
+        ))
+    }
+}
@@ -0,0 +1,293 @@
+use std::{fs::read_to_string, path::PathBuf};
+
+use super::super::annotatedstring::AnnotationType;
+use super::super::settings::config_dir;
+use super::attribute::{rgb, Attribute};
+
+/// The styles of everything the editor draws.
+/// Besides the built-in themes, themes are read from `<config dir>/themes/<name>.theme`, or from a
+/// path when the name contains a `/`. A theme file consists of `key = style` lines, such as
+/// `selection = white on #264f78`, and may start from a built-in theme with `base = <name>`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    /// Everything without a style of its own; the other styles are drawn on top of it
+    pub text: Attribute,
+    pub selection: Attribute,
+    pub search_match: Attribute,
+    pub selected_match: Attribute,
+    pub matching_bracket: Attribute,
+    pub whitespace: Attribute,
+    pub trailing_whitespace: Attribute,
+    pub status_bar: Attribute,
+    /// The markers left of the rows past the end of the file
+    pub gutter: Attribute,
+    pub info_message: Attribute,
+    pub warning_message: Attribute,
+    pub error_message: Attribute,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub const fn dark() -> Self {
+        Self {
+            text: Attribute::new(None, None),
+            selection: Attribute::new(rgb(255, 255, 255), rgb(38, 79, 120)),
+            search_match: Attribute::new(rgb(255, 255, 255), rgb(100, 100, 100)),
+            selected_match: Attribute::new(rgb(0, 0, 0), rgb(255, 251, 0)),
+            matching_bracket: Attribute::new(rgb(255, 255, 255), rgb(0, 128, 128)),
+            whitespace: Attribute::new(rgb(110, 110, 110), None),
+            trailing_whitespace: Attribute::new(rgb(255, 255, 255), rgb(160, 40, 40)),
+            status_bar: Attribute::reversed(),
+            gutter: Attribute::new(None, None),
+            info_message: Attribute::new(None, None),
+            warning_message: Attribute::new(rgb(255, 200, 0), None),
+            error_message: Attribute::new(rgb(255, 85, 85), None),
+        }
+    }
+
+    /// Used instead of the configured theme on terminals without colours, marking everything
+    /// which would otherwise stand out by its colour with reverse video, underlining or bold text.
+    pub const fn monochrome() -> Self {
+        Self {
+            text: Attribute::flags(false, false, false),
+            selection: Attribute::flags(false, true, false),
+            search_match: Attribute::flags(false, false, true),
+            selected_match: Attribute::flags(true, true, false),
+            matching_bracket: Attribute::flags(true, false, true),
+            whitespace: Attribute::flags(false, false, false),
+            trailing_whitespace: Attribute::flags(false, false, true),
+            status_bar: Attribute::flags(false, true, false),
+            gutter: Attribute::flags(false, false, false),
+            info_message: Attribute::flags(false, false, false),
+            warning_message: Attribute::flags(true, false, false),
+            error_message: Attribute::flags(true, false, false),
+        }
+    }
+
+    const fn light() -> Self {
+        Self {
+            text: Attribute::new(None, None),
+            selection: Attribute::new(rgb(0, 0, 0), rgb(173, 214, 255)),
+            search_match: Attribute::new(rgb(0, 0, 0), rgb(210, 210, 210)),
+            selected_match: Attribute::new(rgb(0, 0, 0), rgb(255, 179, 0)),
+            matching_bracket: Attribute::new(rgb(0, 0, 0), rgb(150, 220, 220)),
+            whitespace: Attribute::new(rgb(170, 170, 170), None),
+            trailing_whitespace: Attribute::new(rgb(255, 255, 255), rgb(200, 60, 60)),
+            status_bar: Attribute::new(rgb(255, 255, 255), rgb(70, 70, 70)),
+            gutter: Attribute::new(rgb(150, 150, 150), None),
+            info_message: Attribute::new(None, None),
+            warning_message: Attribute::new(rgb(175, 95, 0), None),
+            error_message: Attribute::new(rgb(200, 0, 0), None),
+        }
+    }
+
+    const fn solarized_dark() -> Self {
+        Self {
+            text: Attribute::new(rgb(131, 148, 150), rgb(0, 43, 54)),
+            selection: Attribute::new(rgb(0, 43, 54), rgb(38, 139, 210)),
+            search_match: Attribute::new(rgb(147, 161, 161), rgb(7, 54, 66)),
+            selected_match: Attribute::new(rgb(0, 43, 54), rgb(181, 137, 0)),
+            matching_bracket: Attribute::new(rgb(0, 43, 54), rgb(42, 161, 152)),
+            whitespace: Attribute::new(rgb(88, 110, 117), None),
+            trailing_whitespace: Attribute::new(rgb(253, 246, 227), rgb(220, 50, 47)),
+            status_bar: Attribute::new(rgb(147, 161, 161), rgb(7, 54, 66)),
+            gutter: Attribute::new(rgb(88, 110, 117), None),
+            info_message: Attribute::new(None, None),
+            warning_message: Attribute::new(rgb(181, 137, 0), None),
+            error_message: Attribute::new(rgb(220, 50, 47), None),
+        }
+    }
+
+    const fn solarized_light() -> Self {
+        Self {
+            text: Attribute::new(rgb(101, 123, 131), rgb(253, 246, 227)),
+            selection: Attribute::new(rgb(253, 246, 227), rgb(38, 139, 210)),
+            search_match: Attribute::new(rgb(88, 110, 117), rgb(238, 232, 213)),
+            selected_match: Attribute::new(rgb(253, 246, 227), rgb(181, 137, 0)),
+            matching_bracket: Attribute::new(rgb(253, 246, 227), rgb(42, 161, 152)),
+            whitespace: Attribute::new(rgb(147, 161, 161), None),
+            trailing_whitespace: Attribute::new(rgb(253, 246, 227), rgb(220, 50, 47)),
+            status_bar: Attribute::new(rgb(88, 110, 117), rgb(238, 232, 213)),
+            gutter: Attribute::new(rgb(147, 161, 161), None),
+            info_message: Attribute::new(None, None),
+            warning_message: Attribute::new(rgb(181, 137, 0), None),
+            error_message: Attribute::new(rgb(220, 50, 47), None),
+        }
+    }
+
+    fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "solarized-dark" => Some(Self::solarized_dark()),
+            "solarized-light" => Some(Self::solarized_light()),
+            _ => None,
+        }
+    }
+
+    /// Loads the theme called `name`, falling back to the default theme for anything missing.
+    /// Returns the theme along with a description of every line which could not be applied.
+    pub fn load(name: &str) -> (Self, Vec<String>) {
+        if let Some(theme) = Self::built_in(name) {
+            return (theme, Vec::new());
+        }
+        let mut theme = Self::default();
+        let path = if name.contains('/') {
+            Some(PathBuf::from(name))
+        } else {
+            config_dir().map(|dir| dir.join("themes").join(format!("{name}.theme")))
+        };
+        let Some(contents) = path.as_ref().and_then(|path| read_to_string(path).ok()) else {
+            return (theme, vec![format!("unknown theme `{name}`")]);
+        };
+        let path = path.unwrap_or_default();
+
+        let mut errors = Vec::new();
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let result = line
+                .split_once('=')
+                .ok_or_else(|| String::from("expected `key = style`"))
+                .and_then(|(key, value)| theme.apply(key.trim(), value.trim()));
+            if let Err(err) = result {
+                errors.push(format!(
+                    "{}:{}: {err}",
+                    path.display(),
+                    line_idx.saturating_add(1)
+                ));
+            }
+        }
+        (theme, errors)
+    }
+
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if key == "base" {
+            *self = Self::built_in(value).ok_or_else(|| format!("unknown theme `{value}`"))?;
+            return Ok(());
+        }
+        let attribute = match key {
+            "text" => &mut self.text,
+            "selection" => &mut self.selection,
+            "match" => &mut self.search_match,
+            "selected_match" => &mut self.selected_match,
+            "matching_bracket" => &mut self.matching_bracket,
+            "whitespace" => &mut self.whitespace,
+            "trailing_whitespace" => &mut self.trailing_whitespace,
+            "status_bar" => &mut self.status_bar,
+            "gutter" => &mut self.gutter,
+            "info_message" => &mut self.info_message,
+            "warning_message" => &mut self.warning_message,
+            "error_message" => &mut self.error_message,
+            _ => return Err(format!("unknown style `{key}`")),
+        };
+        *attribute = value.parse()?;
+        Ok(())
+    }
+
+    pub const fn attribute(&self, annotation_type: AnnotationType) -> Attribute {
+        match annotation_type {
+            AnnotationType::Match => self.search_match,
+            AnnotationType::SelectedMatch => self.selected_match,
+            AnnotationType::Selection => self.selection,
+            AnnotationType::MatchingBracket => self.matching_bracket,
+            AnnotationType::Whitespace => self.whitespace,
+            AnnotationType::TrailingWhitespace => self.trailing_whitespace,
+            AnnotationType::StatusBar => self.status_bar,
+            AnnotationType::Gutter => self.gutter,
+            AnnotationType::InfoMessage => self.info_message,
+            AnnotationType::WarningMessage => self.warning_message,
+            AnnotationType::ErrorMessage => self.error_message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::style::Color;
+    use std::{env, fs, process};
+
+    use super::super::attribute::{rgb, Attribute};
+    use super::Theme;
+
+    /// Loads a theme file called `name` holding `contents`.
+    fn load(name: &str, contents: &str) -> (Theme, Vec<String>) {
+        let dir = env::temp_dir().join(format!("stack-theme-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{name}.theme"));
+        fs::write(&path, contents).unwrap();
+        let (theme, errors) = Theme::load(&path.to_string_lossy());
+        fs::remove_file(&path).unwrap();
+        // Other tests may still be using the directory
+        let _ = fs::remove_dir(&dir);
+        let prefix = format!("{}:", path.display());
+        let errors = errors
+            .into_iter()
+            .map(|err| err.strip_prefix(&prefix).map(str::to_string).unwrap_or(err))
+            .collect();
+        (theme, errors)
+    }
+
+    #[test]
+    fn theme_files_override_only_the_keys_they_set() {
+        let (theme, errors) = load("blue", "# Blue selection\n\nselection = white on #0000ff\n  match=bold\n");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(
+            theme,
+            Theme {
+                selection: Attribute::new(Some(Color::White), rgb(0, 0, 255)),
+                search_match: Attribute::flags(true, false, false),
+                ..Theme::dark()
+            }
+        );
+    }
+
+    #[test]
+    fn theme_files_can_start_from_a_built_in_theme() {
+        let (theme, errors) = load("gutterless", "base = solarized-light\ngutter = default\n");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(
+            theme,
+            Theme {
+                gutter: Attribute::default(),
+                ..Theme::solarized_light()
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_and_skipped() {
+        let (theme, errors) = load("broken", "selection\nshadow = red\ngutter = red\ntext = red on\nbase = neon\n");
+        assert_eq!(
+            errors,
+            [
+                "1: expected `key = style`",
+                "2: unknown style `shadow`",
+                "4: expected a colour after `on`",
+                "5: unknown theme `neon`",
+            ]
+        );
+        assert_eq!(
+            theme,
+            Theme {
+                gutter: Attribute::new(Some(Color::Red), None),
+                ..Theme::dark()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_themes_fall_back_to_the_default() {
+        assert_eq!(Theme::load("light"), (Theme::light(), Vec::new()));
+        let (theme, errors) = Theme::load("/nonexistent/neon.theme");
+        assert_eq!(theme, Theme::default());
+        assert_eq!(errors, ["unknown theme `/nonexistent/neon.theme`"]);
+    }
+}
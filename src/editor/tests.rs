@@ -0,0 +1,589 @@
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::{Mutex, MutexGuard, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::settings::set_test_home;
+use super::terminal::HeadlessBackend;
+use super::arguments::{FileArgument, OpenFile};
+use super::{
+    Arguments, Editor, MacroRegisters, MacroStep, Position, PromptHistory, Size, Terminal,
+};
+
+/// `Terminal` draws to a single backend for the whole process, so only one test may drive an editor at a time.
+static TERMINAL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Drives an editor with scripted input and keeps what it drew.
+struct Harness {
+    editor: Editor,
+    screen: HeadlessBackend,
+    // Dropped last, so that the editor is done drawing before the next test starts
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    /// Starts an editor on a screen of `width` x `height` cells, editing a buffer holding `contents`.
+    fn new(width: usize, height: usize, contents: &str) -> Self {
+        let mut harness = Self::open(width, height, &Arguments::default());
+        harness.editor.view.load_contents(contents);
+        harness.refresh();
+        harness
+    }
+
+    /// Starts an editor on a screen of `width` x `height` cells, as if it was run with `arguments`.
+    fn open(width: usize, height: usize, arguments: &Arguments) -> Self {
+        let lock = TERMINAL_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        // Keep the settings, themes and prompt history of whoever runs the tests out of them
+        set_test_home(Some(Self::home()));
+
+        let screen = HeadlessBackend::new(Size { height, width });
+        Terminal::set_backend(Box::new(screen.clone()));
+        let editor = Editor::new(arguments).unwrap();
+        let mut harness = Self {
+            editor,
+            screen,
+            _lock: lock,
+        };
+        harness.refresh();
+        harness
+    }
+
+    /// Where the files of the tests are kept.
+    fn dir() -> PathBuf {
+        env::temp_dir().join(format!("stack-tests-{}", process::id()))
+    }
+
+    /// The home directory of the editors under test, which holds their settings and state.
+    fn home() -> PathBuf {
+        Self::dir().join("home")
+    }
+
+    fn refresh(&mut self) {
+        self.editor.refresh_status();
+        self.editor.refresh_screen();
+    }
+
+    fn press(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        self.editor
+            .evaluate_event(&Event::Key(KeyEvent::new(code, modifiers)));
+        self.refresh();
+    }
+
+    fn key(&mut self, code: KeyCode) {
+        self.press(code, KeyModifiers::NONE);
+    }
+
+    fn ctrl(&mut self, c: char) {
+        self.press(KeyCode::Char(c), KeyModifiers::CONTROL);
+    }
+
+    fn alt(&mut self, c: char) {
+        self.press(KeyCode::Char(c), KeyModifiers::ALT);
+    }
+
+    fn mouse(&mut self, kind: MouseEventKind, column: u16, row: u16) {
+        self.editor.evaluate_event(&Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }));
+        self.refresh();
+    }
+
+    fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.key(KeyCode::Char(c));
+        }
+    }
+
+    fn text(&self) -> String {
+        self.screen.text()
+    }
+
+    fn styles(&self) -> String {
+        self.screen.styles()
+    }
+
+    fn caret(&self) -> Option<Position> {
+        self.screen.caret()
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        set_test_home(None);
+        let _ = fs::remove_dir_all(Self::dir());
+    }
+}
+
+fn screen(rows: &[&str]) -> String {
+    rows.join("\n")
+}
+
+#[test]
+fn typing_inserts_text_and_marks_the_buffer_modified() {
+    let mut harness = Harness::new(50, 6, "");
+    harness.type_text("Hello");
+    harness.key(KeyCode::Enter);
+    harness.type_text("wörld 日本");
+
+    assert_eq!(harness.text(), screen(&[
+        "Hello",
+        "wörld 日本",
+        "~",
+        "~",
+        "[No Name] - 2 lines (modi… Text UTF-8 LF  2:9  2/2",
+        "HELP: Ctrl-O = open | Ctrl-T = find file | Ctrl-F",
+    ]));
+    assert_eq!(harness.caret(), Some(Position { col: 10, row: 1 }));
+    assert_eq!(harness.screen.title(), "[No Name] - stack");
+}
+
+#[test]
+fn search_highlights_the_matches_while_typing() {
+    let mut harness = Harness::new(50, 6, "fn main() {\n    println!(\"in main\");\n}\n");
+    harness.ctrl('f');
+    harness.type_text("main");
+
+    assert_eq!(harness.text(), screen(&[
+        "fn main() {",
+        "    println!(\"in main\");",
+        "}",
+        "~",
+        "[No Name] - 3 lines        Text UTF-8 LF  1:4  1/3",
+        "Search (Esc to cancel, Up/Down to navigate): main",
+    ]));
+    assert_eq!(harness.styles(), screen(&[
+        "   MMMM",
+        "                 mmmm",
+        "",
+        "g",
+        "==================================================",
+        "",
+    ]));
+    assert_eq!(harness.caret(), Some(Position { col: 49, row: 5 }));
+
+    harness.key(KeyCode::Esc);
+    assert_eq!(harness.text(), screen(&[
+        "fn main() {",
+        "    println!(\"in main\");",
+        "}",
+        "~",
+        "[No Name] - 3 lines        Text UTF-8 LF  1:1  1/3",
+        "HELP: Ctrl-O = open | Ctrl-T = find file | Ctrl-F",
+    ]));
+    assert_eq!(harness.styles(), screen(&[
+        "",
+        "",
+        "",
+        "g",
+        "==================================================",
+        "iiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiii",
+    ]));
+}
+
+#[test]
+fn invalid_line_numbers_are_reported_in_the_message_bar() {
+    let mut harness = Harness::new(50, 5, "one\ntwo\n");
+    harness.ctrl('g');
+    harness.type_text("x1");
+    assert_eq!(harness.text(), screen(&[
+        "one",
+        "two",
+        "~",
+        "[No Name] - 2 lines        Text UTF-8 LF  1:1  1/2",
+        "Go to line (line[:col], +n, -n, %n): x1",
+    ]));
+
+    harness.key(KeyCode::Enter);
+    assert_eq!(harness.text(), screen(&[
+        "one",
+        "two",
+        "~",
+        "[No Name] - 2 lines        Text UTF-8 LF  1:1  1/2",
+        "Invalid line number: `x1`",
+    ]));
+    assert_eq!(harness.styles(), screen(&[
+        "",
+        "",
+        "g",
+        "==================================================",
+        "EEEEEEEEEEEEEEEEEEEEEEEEE",
+    ]));
+}
+
+#[test]
+fn scrolling_moves_the_view_along_with_the_caret() {
+    let lines: Vec<String> = (1..=20).map(|line| format!("line {line}")).collect();
+    let mut harness = Harness::new(40, 6, &lines.join("\n"));
+    for _ in 0..5 {
+        harness.key(KeyCode::Down);
+    }
+    assert_eq!(harness.text(), screen(&[
+        "line 3",
+        "line 4",
+        "line 5",
+        "line 6",
+        "[No Name] - 20… Text UTF-8 LF  6:1  6/20",
+        "HELP: Ctrl-O = open | Ctrl-T = find file",
+    ]));
+    harness.key(KeyCode::PageDown);
+    assert_eq!(harness.text(), screen(&[
+        "line 6",
+        "line 7",
+        "line 8",
+        "line 9",
+        "[No Name] - 20… Text UTF-8 LF  9:1  9/20",
+        "HELP: Ctrl-O = open | Ctrl-T = find file",
+    ]));
+    harness.key(KeyCode::Up);
+    harness.key(KeyCode::PageUp);
+    assert_eq!(harness.text(), screen(&[
+        "line 5",
+        "line 6",
+        "line 7",
+        "line 8",
+        "[No Name] - 20… Text UTF-8 LF  5:1  5/20",
+        "HELP: Ctrl-O = open | Ctrl-T = find file",
+    ]));
+    assert_eq!(harness.caret(), Some(Position { col: 0, row: 0 }));
+}
+
+#[test]
+fn macros_replay_the_recorded_keys() {
+    let mut harness = Harness::new(50, 7, "a\nb\nc\nd");
+    harness.alt('r');
+    harness.type_text("semi");
+    harness.key(KeyCode::Enter);
+    harness.key(KeyCode::End);
+    harness.type_text(";");
+    harness.key(KeyCode::Down);
+    harness.key(KeyCode::Home);
+    assert_eq!(harness.text(), screen(&[
+        "a;",
+        "b",
+        "c",
+        "d",
+        "~",
+        "[No Name] - 4 lines (modi… Text UTF-8 LF  2:1  2/4",
+        "Recording macro semi. Alt-S stops recording.",
+    ]));
+
+    harness.alt('s');
+    harness.alt('p');
+    harness.type_text("2 semi");
+    harness.key(KeyCode::Enter);
+    assert_eq!(harness.text(), screen(&[
+        "a;",
+        "b;",
+        "c;",
+        "d",
+        "~",
+        "[No Name] - 4 lines (modi… Text UTF-8 LF  4:1  4/4",
+        "Recorded macro semi of 4 steps.",
+    ]));
+    assert_eq!(harness.caret(), Some(Position { col: 0, row: 3 }));
+}
+
+#[test]
+#[cfg(unix)]
+fn filtering_replaces_the_lines_with_the_output_of_the_command() {
+    let mut harness = Harness::new(50, 6, "pear\napple\nfig");
+    harness.alt('|');
+    harness.type_text("sort");
+    assert_eq!(harness.text(), screen(&[
+        "pear",
+        "apple",
+        "fig",
+        "~",
+        "[No Name] - 3 lines        Text UTF-8 LF  1:1  1/3",
+        "Filter the whole file through: sort",
+    ]));
+
+    harness.key(KeyCode::Enter);
+    assert_eq!(harness.text(), screen(&[
+        "apple",
+        "fig",
+        "pear",
+        "~",
+        "[No Name] - 3 lines (modi… Text UTF-8 LF  1:1  1/3",
+        "Filtered 3 lines through `sort`.",
+    ]));
+}
+
+#[test]
+#[cfg(unix)]
+fn filtering_a_selection_leaves_out_the_line_it_ends_at_the_start_of() {
+    let mut harness = Harness::new(50, 7, "pear\napple\nfig\nbanana");
+    harness.mouse(MouseEventKind::Down(MouseButton::Left), 0, 0);
+    harness.mouse(MouseEventKind::Drag(MouseButton::Left), 0, 2);
+    harness.alt('|');
+    harness.type_text("sort");
+    assert_eq!(harness.text(), screen(&[
+        "pear",
+        "apple",
+        "fig",
+        "banana",
+        "~",
+        "[N… 11 selected (3 lines)  Text UTF-8 LF  3:1  3/4",
+        "Filter 2 selected lines through: sort",
+    ]));
+
+    harness.key(KeyCode::Enter);
+    assert_eq!(harness.text(), screen(&[
+        "apple",
+        "pear",
+        "fig",
+        "banana",
+        "~",
+        "[No Name] - 4 lines (modi… Text UTF-8 LF  1:1  1/4",
+        "Filtered 2 lines through `sort`.",
+    ]));
+}
+
+#[test]
+#[cfg(unix)]
+fn failing_filters_leave_the_buffer_alone() {
+    let mut harness = Harness::new(60, 6, "pear\napple\nfig");
+    harness.alt('|');
+    harness.type_text("echo broken >&2; exit 3");
+    harness.key(KeyCode::Enter);
+    assert_eq!(harness.text(), screen(&[
+        "pear",
+        "apple",
+        "fig",
+        "~",
+        "[No Name] - 3 lines                  Text UTF-8 LF  1:1  1/3",
+        "`echo broken >&2; exit 3` exited with status 3: broken",
+    ]));
+    assert_eq!(harness.styles(), screen(&[
+        "",
+        "",
+        "",
+        "g",
+        "============================================================",
+        "EEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEEE",
+    ]));
+}
+
+#[test]
+fn deleting_past_the_end_of_the_text_keeps_the_buffer_unmodified() {
+    let mut harness = Harness::new(50, 4, "one\ntwo");
+    harness.key(KeyCode::Down);
+    harness.key(KeyCode::End);
+    harness.press(KeyCode::Delete, KeyModifiers::CONTROL);
+    harness.key(KeyCode::Delete);
+    assert_eq!(harness.text(), screen(&[
+        "one",
+        "two",
+        "[No Name] - 2 lines        Text UTF-8 LF  2:4  2/2",
+        "HELP: Ctrl-O = open | Ctrl-T = find file | Ctrl-F",
+    ]));
+}
+
+#[test]
+fn saving_to_a_completed_home_path_writes_into_the_home_directory() {
+    let mut harness = Harness::new(50, 4, "notes");
+    let home = Harness::home();
+    fs::create_dir_all(home.join("documents")).unwrap();
+
+    harness.ctrl('s');
+    harness.type_text("~/doc");
+    harness.key(KeyCode::Tab);
+    harness.type_text("todo.txt");
+    assert_eq!(harness.text(), screen(&[
+        "notes",
+        "~",
+        "[No Name] - 1 lines        Text UTF-8 LF  1:1  1/1",
+        "Save as: ~/documents/todo.txt",
+    ]));
+
+    harness.key(KeyCode::Enter);
+    assert_eq!(harness.text(), screen(&[
+        "notes",
+        "~",
+        "todo.txt - 1 lines         Text UTF-8 LF  1:1  1/1",
+        "File saved successfully.",
+    ]));
+    assert_eq!(fs::read_to_string(home.join("documents/todo.txt")).unwrap(), "notes\n");
+}
+
+#[test]
+fn generated_views_cannot_be_made_editable() {
+    let mut harness = Harness::new(50, 5, "text");
+    harness.alt('m');
+    harness.ctrl('r');
+    harness.type_text("x");
+    harness.ctrl('s');
+    // The first row holds the time the help message was shown
+    let text = harness.text();
+    let rows: Vec<&str> = text.lines().skip(1).collect();
+    assert_eq!(rows, [
+        "~",
+        "~",
+        "[Messages] [RO] - 1 lines  Text UTF-8 LF  1:1  1/1",
+        "[Messages] can't be edited.",
+    ]);
+    assert!(text.lines().next().is_some_and(|row| row.ends_with("INFO    HELP: Ctrl-O = open | Ctrl-T = fi")));
+}
+
+#[test]
+fn closing_a_scratch_view_returns_to_where_it_was_opened() {
+    let mut harness = Harness::new(50, 5, "first");
+    let mut second = harness.editor.new_view();
+    second.load_contents("second");
+    harness.editor.other_views.push_back(second);
+    let third = Harness::dir().join("third.txt");
+    fs::create_dir_all(Harness::dir()).unwrap();
+    fs::write(&third, "third\n").unwrap();
+
+    harness.alt('m');
+    harness.press(KeyCode::PageUp, KeyModifiers::CONTROL);
+    harness.ctrl('o');
+    harness.type_text(&third.to_string_lossy());
+    harness.key(KeyCode::Enter);
+    harness.press(KeyCode::PageDown, KeyModifiers::CONTROL);
+    assert!(harness.text().contains("[Messages]"));
+
+    harness.key(KeyCode::Esc);
+    assert!(harness.text().starts_with("first\n"));
+}
+
+#[test]
+fn directory_listings_create_files_but_cannot_be_edited() {
+    let mut harness = Harness::new(60, 6, "");
+    let dir = Harness::dir().join("browsed");
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    harness.ctrl('o');
+    harness.type_text(&dir.to_string_lossy());
+    harness.key(KeyCode::Enter);
+    harness.key(KeyCode::Delete);
+    harness.ctrl('r');
+    harness.key(KeyCode::Delete);
+    let text = harness.text();
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows[..3], ["../", "sub/", "~"]);
+    assert_eq!(rows[5], "Directory listings can't be edited. n = new file | m = new d");
+
+    harness.type_text("nnotes.txt");
+    harness.key(KeyCode::Enter);
+    let text = harness.text();
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows[..3], ["../", "sub/", "notes.txt"]);
+    assert_eq!(rows[5], "Created notes.txt.");
+    assert_eq!(harness.caret(), Some(Position { col: 0, row: 2 }));
+    assert!(dir.join("notes.txt").is_file());
+}
+
+#[test]
+fn bracket_pairs_follow_edits_which_keep_the_caret_in_place() {
+    let mut harness = Harness::new(50, 4, "x(a)");
+    assert_eq!(harness.styles().lines().next(), Some(""));
+
+    harness.key(KeyCode::Delete);
+    assert_eq!(harness.styles().lines().next(), Some("b b"));
+}
+
+#[test]
+fn the_file_finder_lists_the_project_files_in_the_background() {
+    let mut harness = Harness::new(50, 6, "");
+    let project = Harness::dir().join("project");
+    for file in ["Cargo.toml", "macros/Cargo.toml", "src/cargo_tools.rs", "target/Cargo.toml"] {
+        let path = project.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+    harness.editor.project_root = project;
+
+    harness.ctrl('t');
+    harness.type_text("cargotoml");
+    assert!(!harness.text().contains("Cargo.toml"));
+
+    let start = Instant::now();
+    while harness.editor.project_files.is_some() && start.elapsed() < Duration::from_secs(10) {
+        harness.editor.receive_project_files();
+        thread::sleep(Duration::from_millis(10));
+    }
+    harness.refresh();
+    // Shorter paths come first among equally good matches
+    assert_eq!(harness.text(), screen(&[
+        "> Cargo.toml",
+        "  macros/Cargo.toml",
+        "",
+        "",
+        "2/3 files",
+        "Find file: cargotoml",
+    ]));
+
+    // The files are opened in the project, wherever the editor runs
+    harness.key(KeyCode::Enter);
+    assert!(harness.text().contains("Cargo.toml - 0 lines"));
+}
+
+#[test]
+fn editors_saving_their_prompt_history_keep_each_others_entries() {
+    // Only for the state directory the harness sets up
+    let _harness = Harness::new(50, 4, "");
+    let mut first = PromptHistory::load();
+    let mut second = PromptHistory::load();
+    first.push("find", "one");
+    first.save().unwrap();
+    second.push("find", "two");
+    second.push("goto_line", "12");
+    second.save().unwrap();
+
+    assert_eq!(PromptHistory::load().entries("find"), ["one", "two"]);
+    assert_eq!(PromptHistory::load().entries("goto_line"), ["12"]);
+    assert_eq!(second.entries("find"), ["one", "two"]);
+}
+
+#[test]
+fn editors_saving_their_macros_keep_each_others_macros() {
+    let _harness = Harness::new(50, 4, "");
+    let mut first = MacroRegisters::load();
+    let mut second = MacroRegisters::load();
+    first.set("a", vec![MacroStep::Insert('a')]);
+    first.save("a").unwrap();
+    second.set("", vec![MacroStep::Insert('x')]);
+    second.set("b", vec![MacroStep::Paste(String::from("b\tb\n"))]);
+    second.save("b").unwrap();
+
+    let saved = MacroRegisters::load();
+    assert_eq!(saved.get("a"), [MacroStep::Insert('a')]);
+    assert_eq!(saved.get("b"), [MacroStep::Paste(String::from("b\tb\n"))]);
+    assert_eq!(saved.get(""), []);
+    assert_eq!(second.get("a"), [MacroStep::Insert('a')]);
+    assert_eq!(second.get(""), [MacroStep::Insert('x')]);
+}
+
+#[test]
+fn every_file_which_could_not_be_opened_is_reported() {
+    let missing = |name: &str| OpenFile {
+        file: FileArgument::Path(Harness::dir().join(name).to_string_lossy().into_owned()),
+        start_position: None,
+    };
+    let arguments = Arguments {
+        files: vec![missing("first.txt"), missing("second.txt")],
+        read_only: false,
+    };
+    let mut harness = Harness::open(80, 6, &arguments);
+    assert_eq!(
+        harness.text().lines().last(),
+        Some("2 problems opening the files. Alt-M shows them all.")
+    );
+
+    harness.alt('m');
+    let text = harness.text();
+    for name in ["first.txt", "second.txt"] {
+        let message = format!("ERROR   Could not open file: {}", Harness::dir().join(name).display());
+        assert!(text.lines().any(|line| line.ends_with(&message)), "{text}");
+    }
+}
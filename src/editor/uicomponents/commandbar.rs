@@ -1,53 +1,315 @@
 use std::{cmp::min, io::Error};
 
-use super::super::{Line, Size, Terminal};
+use unicode_width::UnicodeWidthStr;
+
+use super::super::pathcompletion::{candidate_name, common_prefix, path_candidates};
+use super::super::{
+    Col, GraphemeIdx, Line, PromptHistory, Size, Terminal, WordRules, DEFAULT_TAB_WIDTH,
+};
 use super::UIComponent;
 
+/// The state of path completion, kept while the value is what the last completion produced.
+struct Completion {
+    candidates: Vec<String>,
+    selected: Option<usize>,
+    value: String,
+}
+
 #[derive(Default)]
 pub struct CommandBar {
     prompt: String,
     value: Line,
+    caret: GraphemeIdx,
+    /// The first column of the value which is visible, for values wider than the bar
+    scroll_col: Col,
+    history: PromptHistory,
+    /// Which of the prompt histories the current prompt uses
+    history_kind: Option<&'static str>,
+    /// The history entry being shown, while the user is browsing the history
+    history_idx: Option<usize>,
+    /// What the user had typed before browsing the history
+    draft: String,
+    completion: Option<Completion>,
     needs_redraw: bool,
     size: Size,
 }
 
 impl CommandBar {
     pub fn caret_position_col(&self) -> usize {
-        let max_width = self
-            .prompt
-            .len()
-            .saturating_add(self.value.grapheme_count());
-        min(max_width, self.size.width)
+        let caret_col = self
+            .value
+            .width_until(self.caret, DEFAULT_TAB_WIDTH)
+            .saturating_sub(self.scroll_col);
+        min(
+            self.prompt.width().saturating_add(caret_col),
+            self.size.width,
+        )
     }
 
     pub fn value(&self) -> String {
         self.value.to_string()
     }
 
-    pub fn set_prompt(&mut self, prompt: &str) {
+    pub fn set_history(&mut self, history: PromptHistory) {
+        self.history = history;
+    }
+
+    pub fn set_prompt(&mut self, prompt: &str, history_kind: Option<&'static str>) {
         self.prompt = prompt.to_string();
+        self.history_kind = history_kind;
+        self.history_idx = None;
+        self.completion = None;
+        self.scroll_caret_into_view();
         self.set_needs_redraw(true);
     }
 
+    /// Replaces the value with a suggestion and puts the caret at its end.
+    pub fn set_value(&mut self, value: &str) {
+        self.clear_value();
+        self.insert_str(value);
+    }
+
     pub fn clear_value(&mut self) {
         self.value = Line::default();
+        self.history_idx = None;
+        self.completion = None;
+        self.caret = 0;
+        self.scroll_col = 0;
         self.set_needs_redraw(true);
     }
 
-    pub fn append_char(&mut self, c: char) {
-        self.value.append_char(c);
+    /// Adds the current value to the history of the current prompt and writes the history to disk.
+    pub fn remember_value(&mut self) -> Result<(), Error> {
+        let Some(kind) = self.history_kind else {
+            return Ok(());
+        };
+        self.history.push(kind, &self.value.to_string());
+        self.history.save()
     }
 
-    pub fn redraw(&mut self) {
-        self.set_needs_redraw(true);
+    /// Replaces the value with the next older history entry.
+    pub fn history_previous(&mut self) {
+        let entry_count = self.history_entries().len();
+        let idx = match self.history_idx {
+            None if entry_count == 0 => return,
+            None => {
+                self.draft = self.value.to_string();
+                entry_count.saturating_sub(1)
+            }
+            Some(idx) => idx.saturating_sub(1),
+        };
+        self.show_history_entry(Some(idx));
     }
 
-    pub fn delete_last(&mut self) {
-        self.value.delete_last();
+    /// Replaces the value with the next newer history entry, or with the draft after the newest one.
+    pub fn history_next(&mut self) {
+        let Some(idx) = self.history_idx else {
+            return;
+        };
+        let next = idx.saturating_add(1);
+        if next < self.history_entries().len() {
+            self.show_history_entry(Some(next));
+        } else {
+            self.show_history_entry(None);
+        }
+    }
+
+    fn history_entries(&self) -> &[String] {
+        self.history_kind
+            .map_or(&[], |kind| self.history.entries(kind))
+    }
+
+    fn show_history_entry(&mut self, idx: Option<usize>) {
+        let value = match idx {
+            Some(idx) => self.history_entries().get(idx).cloned().unwrap_or_default(),
+            None => self.draft.clone(),
+        };
+        self.history_idx = idx;
+        self.value = Line::from(&value);
+        self.caret = self.value.grapheme_count();
+        self.changed();
+    }
+
+    /// Completes the value as a file system path. Pressing it again cycles through the candidates
+    /// if there are several. Returns whether there was anything to complete to.
+    pub fn complete_path(&mut self) -> bool {
+        let current = self.value.to_string();
+        let completion = match self.completion.take() {
+            Some(completion) if completion.value == current => {
+                let selected = completion
+                    .selected
+                    .map_or(0, |selected| selected.saturating_add(1))
+                    .checked_rem(completion.candidates.len())
+                    .unwrap_or(0);
+                Completion {
+                    value: completion
+                        .candidates
+                        .get(selected)
+                        .cloned()
+                        .unwrap_or_default(),
+                    selected: Some(selected),
+                    candidates: completion.candidates,
+                }
+            }
+            _ => {
+                let candidates = path_candidates(&current);
+                let prefix = common_prefix(&candidates);
+                let (selected, value) = match candidates.first() {
+                    None => return false,
+                    Some(only) if candidates.len() == 1 => (Some(0), only.clone()),
+                    // Extend the value as far as it is unambiguous before cycling
+                    Some(_) if prefix.len() > current.len() => (None, prefix),
+                    Some(first) => (Some(0), first.clone()),
+                };
+                Completion {
+                    candidates,
+                    selected,
+                    value,
+                }
+            }
+        };
+        self.value = Line::from(&completion.value);
+        self.caret = self.value.grapheme_count();
+        self.completion = Some(completion);
+        self.changed();
+        true
+    }
+
+    /// Lists the completion candidates, with the selected one in brackets, while there are several.
+    pub fn completion_hint(&self) -> Option<String> {
+        let completion = self.completion.as_ref().filter(|completion| {
+            completion.candidates.len() > 1 && completion.value == self.value.to_string()
+        })?;
+        let names: Vec<String> = completion
+            .candidates
+            .iter()
+            .enumerate()
+            .map(|(idx, candidate)| {
+                let name = candidate_name(candidate);
+                if completion.selected == Some(idx) {
+                    format!("[{name}]")
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect();
+        Some(names.join("  "))
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.value.insert_char(c, self.caret);
+        self.caret = self.caret.saturating_add(1);
+        self.changed();
+    }
+
+    /// Inserts pasted text, dropping line breaks since the prompt is a single line.
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| *c != '\n' && *c != '\r').collect();
+        let inserted = Line::from(&text);
+        let remainder = self.value.split(self.caret);
+        self.value.append(&inserted);
+        self.value.append(&remainder);
+        self.caret = self.caret.saturating_add(inserted.grapheme_count());
+        self.changed();
+    }
+
+    pub fn move_left(&mut self) {
+        self.caret = self.caret.saturating_sub(1);
+        self.changed();
+    }
+
+    pub fn move_right(&mut self) {
+        self.caret = min(self.caret.saturating_add(1), self.value.grapheme_count());
+        self.changed();
+    }
+
+    pub fn move_to_start(&mut self) {
+        self.caret = 0;
+        self.changed();
+    }
+
+    pub fn move_to_end(&mut self) {
+        self.caret = self.value.grapheme_count();
+        self.changed();
+    }
+
+    pub fn move_word_left(&mut self, rules: WordRules) {
+        self.caret = self.value.previous_word_start(self.caret, rules).unwrap_or(0);
+        self.changed();
+    }
+
+    pub fn move_word_right(&mut self, rules: WordRules) {
+        self.caret = self
+            .value
+            .next_word_end(self.caret, rules)
+            .unwrap_or_else(|| self.value.grapheme_count());
+        self.changed();
+    }
+
+    pub fn delete_backward(&mut self) {
+        if self.caret > 0 {
+            self.caret = self.caret.saturating_sub(1);
+            self.value.delete(self.caret);
+            self.changed();
+        }
     }
 
     pub fn delete(&mut self) {
-        self.value.delete(self.caret_position_col());
+        if self.caret < self.value.grapheme_count() {
+            self.value.delete(self.caret);
+            self.changed();
+        }
+    }
+
+    pub fn delete_word_backward(&mut self, rules: WordRules) {
+        let start = self.value.previous_word_start(self.caret, rules).unwrap_or(0);
+        self.delete_range(start, self.caret);
+    }
+
+    pub fn delete_word(&mut self, rules: WordRules) {
+        let end = self
+            .value
+            .next_word_end(self.caret, rules)
+            .unwrap_or_else(|| self.value.grapheme_count());
+        self.delete_range(self.caret, end);
+    }
+
+    /// Deletes everything before the caret.
+    pub fn kill_to_start(&mut self) {
+        self.delete_range(0, self.caret);
+    }
+
+    /// Deletes everything after the caret.
+    pub fn kill_to_end(&mut self) {
+        self.delete_range(self.caret, self.value.grapheme_count());
+    }
+
+    fn delete_range(&mut self, start: GraphemeIdx, end: GraphemeIdx) {
+        for _ in start..end {
+            self.value.delete(start);
+        }
+        self.caret = start;
+        self.changed();
+    }
+
+    fn changed(&mut self) {
+        self.scroll_caret_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    fn area_for_value(&self) -> usize {
+        self.size.width.saturating_sub(self.prompt.width())
+    }
+
+    fn scroll_caret_into_view(&mut self) {
+        let caret_col = self.value.width_until(self.caret, DEFAULT_TAB_WIDTH);
+        let area = self.area_for_value();
+        if caret_col < self.scroll_col {
+            self.scroll_col = caret_col;
+        } else if caret_col >= self.scroll_col.saturating_add(area) {
+            // Keep the caret in the last column, which is left free for it
+            self.scroll_col = caret_col.saturating_sub(area).saturating_add(1);
+        }
     }
 }
 
@@ -62,18 +324,18 @@ impl UIComponent for CommandBar {
 
     fn set_size(&mut self, size: Size) {
         self.size = size;
+        self.scroll_caret_into_view();
     }
 
     fn draw(&mut self, origin: usize) -> Result<(), Error> {
-        let area_for_value = self.size.width.saturating_sub(self.prompt.len());
-        let value_end = self.value.width();
-        let value_start = value_end.saturating_sub(area_for_value);
+        let value_end = self.scroll_col.saturating_add(self.area_for_value());
         let message = format!(
             "{}{}",
             self.prompt,
-            self.value.get_visible_graphemes(value_start..value_end)
+            self.value
+                .get_visible_graphemes(self.scroll_col..value_end, DEFAULT_TAB_WIDTH)
         );
-        let to_print = if message.len() <= self.size.width {
+        let to_print = if message.width() <= self.size.width {
             message
         } else {
             String::new()
@@ -81,3 +343,99 @@ impl UIComponent for CommandBar {
         Terminal::print_row(origin, &to_print)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandBar, PromptHistory, Size, UIComponent, WordRules};
+
+    fn command_bar(width: usize, value: &str) -> CommandBar {
+        let mut command_bar = CommandBar::default();
+        command_bar.resize(Size { height: 1, width });
+        command_bar.set_prompt("> ", Some("find"));
+        command_bar.set_value(value);
+        command_bar
+    }
+
+    #[test]
+    fn words_are_deleted_up_to_the_next_stop() {
+        let rules = WordRules::default();
+        let mut command_bar = command_bar(40, "open src/main.rs");
+        command_bar.delete_word_backward(rules);
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), ("open src/main.", 14));
+        command_bar.delete_word_backward(rules);
+        command_bar.delete_word_backward(rules);
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), ("open src/", 9));
+
+        command_bar.move_to_start();
+        command_bar.delete_word(rules);
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), (" src/", 0));
+        command_bar.delete_word(rules);
+        command_bar.delete_word(rules);
+        command_bar.delete_word(rules);
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), ("", 0));
+    }
+
+    #[test]
+    fn killing_deletes_everything_before_or_after_the_caret() {
+        let mut command_bar = command_bar(40, "hello world");
+        command_bar.move_word_left(WordRules::default());
+        command_bar.kill_to_end();
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), ("hello ", 6));
+        command_bar.move_left();
+        command_bar.kill_to_start();
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), (" ", 0));
+        command_bar.kill_to_start();
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), (" ", 0));
+    }
+
+    #[test]
+    fn browsing_the_history_restores_the_draft_afterwards() {
+        let mut history = PromptHistory::default();
+        history.push("find", "one");
+        history.push("find", "two");
+        history.push("goto_line", "12");
+        let mut command_bar = command_bar(40, "");
+        command_bar.set_history(history);
+        command_bar.insert_str("dra");
+
+        command_bar.history_previous();
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), ("two", 3));
+        command_bar.history_previous();
+        command_bar.history_previous();
+        assert_eq!(command_bar.value(), "one");
+        command_bar.history_next();
+        assert_eq!(command_bar.value(), "two");
+        command_bar.history_next();
+        assert_eq!((command_bar.value().as_str(), command_bar.caret), ("dra", 3));
+        command_bar.history_next();
+        assert_eq!(command_bar.value(), "dra");
+
+        // Prompts without a history have nothing to browse
+        command_bar.set_prompt("Save as: ", None);
+        command_bar.history_previous();
+        assert_eq!(command_bar.value(), "dra");
+    }
+
+    #[test]
+    fn long_values_scroll_to_keep_the_caret_visible() {
+        // Eight columns for the value, the last one left free for the caret
+        let mut command_bar = command_bar(10, "abcdefghij");
+        assert_eq!((command_bar.scroll_col, command_bar.caret_position_col()), (3, 9));
+        command_bar.move_to_start();
+        assert_eq!((command_bar.scroll_col, command_bar.caret_position_col()), (0, 2));
+        command_bar.move_right();
+        command_bar.move_right();
+        assert_eq!((command_bar.scroll_col, command_bar.caret_position_col()), (0, 4));
+        command_bar.move_to_end();
+        command_bar.move_word_left(WordRules::default());
+        assert_eq!((command_bar.scroll_col, command_bar.caret_position_col()), (0, 2));
+
+        // Wide graphemes scroll by their width
+        command_bar.set_value("中文中文中");
+        assert_eq!((command_bar.scroll_col, command_bar.caret_position_col()), (3, 9));
+        command_bar.resize(Size { height: 1, width: 6 });
+        assert_eq!((command_bar.scroll_col, command_bar.caret_position_col()), (7, 5));
+        command_bar.clear_value();
+        assert_eq!((command_bar.scroll_col, command_bar.caret_position_col()), (0, 2));
+    }
+}
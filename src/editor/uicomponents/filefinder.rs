@@ -0,0 +1,181 @@
+use std::{cmp::Reverse, io::Error};
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::super::fuzzy::fuzzy_match;
+use super::super::{AnnotatedString, AnnotationType, ByteIdx, Size, Terminal};
+use super::UIComponent;
+
+const SELECTED_PREFIX: &str = "> ";
+const PREFIX: &str = "  ";
+
+/// A file matching the query, with the positions of the matched characters for highlighting.
+struct FinderMatch {
+    file_idx: usize,
+    score: i32,
+    positions: Vec<ByteIdx>,
+}
+
+/// Lists the files of the project which match the query typed into the prompt, best match first.
+#[derive(Default)]
+pub struct FileFinder {
+    files: Vec<String>,
+    query: String,
+    matches: Vec<FinderMatch>,
+    selected: usize,
+    /// The first match which is visible
+    scroll_offset: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl FileFinder {
+    /// Starts over with `files` and an empty query, which matches all of them.
+    pub fn set_files(&mut self, files: Vec<String>) {
+        self.files = files;
+        self.query = String::new();
+        self.matches = (0..self.files.len())
+            .map(|file_idx| FinderMatch {
+                file_idx,
+                score: 0,
+                positions: Vec::new(),
+            })
+            .collect();
+        self.select(0);
+        self.set_needs_redraw(true);
+    }
+
+    pub fn set_query(&mut self, query: &str) {
+        if query == self.query {
+            return;
+        }
+        // A longer query can only match fewer files, so only the current matches need to be scored again
+        let candidates: Vec<usize> = if !self.query.is_empty() && query.starts_with(&self.query) {
+            self.matches.iter().map(|found| found.file_idx).collect()
+        } else {
+            (0..self.files.len()).collect()
+        };
+        let files = &self.files;
+        self.matches = candidates
+            .into_iter()
+            .filter_map(|file_idx| {
+                let found = fuzzy_match(query, files.get(file_idx)?)?;
+                Some(FinderMatch {
+                    file_idx,
+                    score: found.score,
+                    positions: found.positions,
+                })
+            })
+            .collect();
+        // Equally good matches are listed with the shorter paths first
+        self.matches.sort_by_key(|found| {
+            let len = files.get(found.file_idx).map_or(0, String::len);
+            (Reverse(found.score), len, found.file_idx)
+        });
+        self.query = query.to_string();
+        self.select(0);
+        self.set_needs_redraw(true);
+    }
+
+    pub fn selected_file(&self) -> Option<&str> {
+        let found = self.matches.get(self.selected)?;
+        self.files.get(found.file_idx).map(String::as_str)
+    }
+
+    pub fn select_next(&mut self, by: usize) {
+        self.select(self.selected.saturating_add(by));
+    }
+
+    pub fn select_previous(&mut self, by: usize) {
+        self.select(self.selected.saturating_sub(by));
+    }
+
+    /// Describes how many of the files match, such as `12/345 files`.
+    pub fn summary(&self) -> String {
+        format!("{}/{} files", self.matches.len(), self.files.len())
+    }
+
+    fn select(&mut self, idx: usize) {
+        self.selected = idx.min(self.matches.len().saturating_sub(1));
+        let height = self.size.height;
+        if self.selected < self.scroll_offset {
+            self.scroll_offset = self.selected;
+        } else if height > 0 && self.selected >= self.scroll_offset.saturating_add(height) {
+            self.scroll_offset = self.selected.saturating_sub(height.saturating_sub(1));
+        }
+        self.set_needs_redraw(true);
+    }
+
+    /// Builds the row showing `found`, keeping the end of paths which are too wide.
+    fn row_for(&self, found: &FinderMatch, is_selected: bool) -> AnnotatedString {
+        let path = self.files.get(found.file_idx).map_or("", String::as_str);
+        let prefix = if is_selected { SELECTED_PREFIX } else { PREFIX };
+        let width = self.size.width;
+        let mut row = AnnotatedString::from(&format!("{prefix}{path}"));
+        if is_selected {
+            row.add_annotation(AnnotationType::Selection, 0, prefix.len().saturating_add(path.len()));
+        }
+        for &position in &found.positions {
+            let start = prefix.len().saturating_add(position);
+            let len = path
+                .get(position..)
+                .and_then(|rest| rest.chars().next())
+                .map_or(0, char::len_utf8);
+            row.add_annotation(AnnotationType::Match, start, start.saturating_add(len));
+        }
+
+        let path_width = path.width();
+        let available = width.saturating_sub(prefix.width());
+        if path_width > available {
+            // Leave room for the ellipsis
+            let overflow = path_width.saturating_sub(available).saturating_add(1);
+            let mut cut_width: usize = 0;
+            let cut = path
+                .grapheme_indices(true)
+                .find_map(|(idx, grapheme)| {
+                    if cut_width >= overflow {
+                        return Some(idx);
+                    }
+                    cut_width = cut_width.saturating_add(grapheme.width());
+                    None
+                })
+                .unwrap_or(path.len());
+            row.replace(prefix.len(), prefix.len().saturating_add(cut), "…");
+        } else if is_selected {
+            row.append(&" ".repeat(available.saturating_sub(path_width)), AnnotationType::Selection);
+        }
+        row
+    }
+}
+
+impl UIComponent for FileFinder {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+        self.select(self.selected);
+    }
+
+    fn draw(&mut self, origin_row: usize) -> Result<(), Error> {
+        for row in 0..self.size.height {
+            let match_idx = self.scroll_offset.saturating_add(row);
+            let screen_row = origin_row.saturating_add(row);
+            if let Some(found) = self.matches.get(match_idx) {
+                let annotated = self.row_for(found, match_idx == self.selected);
+                Terminal::print_annotated_row(screen_row, &annotated)?;
+            } else if row == 0 {
+                Terminal::print_row(screen_row, "  No matching files")?;
+            } else {
+                Terminal::print_row(screen_row, "")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,48 +1,104 @@
 use std::{
+    collections::VecDeque,
     io::Error,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use super::super::{Size, Terminal};
+use super::super::{AnnotatedString, AnnotationType, Severity, Size, Terminal, MESSAGE_HISTORY_LIMIT};
 use super::UIComponent;
 
 const DEFAULT_DURATION: Duration = Duration::new(5, 0);
 
 struct Message {
     text: String,
+    severity: Severity,
     time: Instant,
+    duration: Duration,
 }
 impl Default for Message {
     fn default() -> Self {
         Self {
             text: String::new(),
+            severity: Severity::Info,
             time: Instant::now(),
+            duration: DEFAULT_DURATION,
         }
     }
 }
 
 impl Message {
     fn is_expired(&self) -> bool {
-        Instant::now().duration_since(self.time) > DEFAULT_DURATION
+        Instant::now().duration_since(self.time) > self.duration
+    }
+}
+
+/// A message which was shown, kept so it can be read again later.
+struct HistoryEntry {
+    text: String,
+    severity: Severity,
+    logged_at: SystemTime,
+}
+
+impl HistoryEntry {
+    /// Formats the entry as `HH:MM:SS SEVERITY text`, with the time in UTC.
+    fn to_line(&self) -> String {
+        let seconds = self
+            .logged_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        let seconds_of_day = seconds.checked_rem(86_400).unwrap_or(0);
+        let hours = seconds_of_day.checked_div(3600).unwrap_or(0);
+        let minutes = seconds_of_day.checked_rem(3600).unwrap_or(0).checked_div(60).unwrap_or(0);
+        let seconds = seconds_of_day.checked_rem(60).unwrap_or(0);
+        format!(
+            "{hours:02}:{minutes:02}:{seconds:02} {:<7} {}",
+            self.severity, self.text
+        )
     }
 }
 
 #[derive(Default)]
 pub struct MessageBar {
     current_message: Message,
+    history: VecDeque<HistoryEntry>,
     needs_redraw: bool,
     cleared_after_expiry: bool, //ensures we can properly hide expired messages
 }
 
 impl MessageBar {
-    pub fn update_message(&mut self, new_message: &str) {
+    pub fn update_message(&mut self, severity: Severity, new_message: &str, duration: Duration) {
         self.current_message = Message {
             text: new_message.to_string(),
+            severity,
             time: Instant::now(),
+            duration,
         };
+        if self.history.len() >= MESSAGE_HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(HistoryEntry {
+            text: new_message.to_string(),
+            severity,
+            logged_at: SystemTime::now(),
+        });
         self.cleared_after_expiry = false;
         self.set_needs_redraw(true);
     }
+
+    /// Hides the current message without adding anything to the history.
+    pub fn clear_message(&mut self) {
+        self.current_message = Message::default();
+        self.set_needs_redraw(true);
+    }
+
+    /// Returns the history, oldest message first, one message per line.
+    pub fn history(&self) -> String {
+        self.history
+            .iter()
+            .map(HistoryEntry::to_line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 impl UIComponent for MessageBar {
@@ -63,6 +119,14 @@ impl UIComponent for MessageBar {
             &self.current_message.text
         };
 
-        Terminal::print_row(origin, message)
+        let annotation_type = match self.current_message.severity {
+            _ if message.is_empty() => return Terminal::print_row(origin, message),
+            Severity::Info => AnnotationType::InfoMessage,
+            Severity::Warning => AnnotationType::WarningMessage,
+            Severity::Error => AnnotationType::ErrorMessage,
+        };
+        let mut annotated = AnnotatedString::from(message);
+        annotated.add_annotation(annotation_type, 0, message.len());
+        Terminal::print_annotated_row(origin, &annotated)
     }
 }
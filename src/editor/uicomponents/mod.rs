@@ -1,11 +1,13 @@
 mod commandbar;
+mod filefinder;
 mod messagebar;
 mod statusbar;
 mod view;
 mod uicomponent;
 
 pub use commandbar::CommandBar;
+pub use filefinder::FileFinder;
 pub use messagebar::MessageBar;
 pub use statusbar::StatusBar;
-pub use view::View;
+pub use view::{entry_name, Buffer, FileOperation, View};
 pub use uicomponent::UIComponent;
@@ -1,11 +1,18 @@
 use std::io::Error;
 
-use super::super::{DocumentStatus, Size, Terminal};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::super::{AnnotatedString, AnnotationType, DocumentStatus, Size, StatusFormat, Terminal};
 use super::UIComponent;
 
 #[derive(Default)]
 pub struct StatusBar {
     current_status: DocumentStatus,
+    left_format: StatusFormat,
+    right_format: StatusFormat,
+    /// Shown instead of the status, such as the candidates of a path completion
+    hint: Option<String>,
     needs_redraw: bool,
     size: Size,
 }
@@ -17,6 +24,73 @@ impl StatusBar {
             self.set_needs_redraw(true);
         }
     }
+
+    pub fn set_hint(&mut self, hint: Option<String>) {
+        if hint != self.hint {
+            self.hint = hint;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    pub fn set_format(&mut self, left_format: StatusFormat, right_format: StatusFormat) {
+        self.left_format = left_format;
+        self.right_format = right_format;
+        self.set_needs_redraw(true);
+    }
+
+    fn print_bar(origin_row: usize, text: &str) -> Result<(), Error> {
+        let mut annotated = AnnotatedString::from(text);
+        annotated.add_annotation(AnnotationType::StatusBar, 0, text.len());
+        Terminal::print_annotated_row(origin_row, &annotated)
+    }
+
+    /// The text of the bar, filling its width.
+    fn text(&self) -> String {
+        let width = self.size.width;
+        if let Some(hint) = &self.hint {
+            let hint = Self::truncate(hint, width);
+            let padding = width.saturating_sub(hint.width());
+            return format!("{hint}{}", " ".repeat(padding));
+        }
+        let left = self.left_format.render(&self.current_status);
+        let right = self.right_format.render(&self.current_status);
+
+        // Keep the right side whole for as long as possible, since it holds the position,
+        // and shorten the left side to make room for it.
+        if left.width().saturating_add(right.width()) < width {
+            let remainder_len = width.saturating_sub(left.width());
+            let padding = remainder_len.saturating_sub(right.width());
+            format!("{left}{}{right}", " ".repeat(padding))
+        } else if right.width().saturating_add(2) <= width {
+            let left = Self::truncate(&left, width.saturating_sub(right.width()).saturating_sub(1));
+            let padding = width.saturating_sub(left.width()).saturating_sub(right.width());
+            format!("{left}{}{right}", " ".repeat(padding))
+        } else {
+            Self::truncate(&right, width)
+        }
+    }
+
+    /// Cuts `text` down to `width` columns, marking the cut with an ellipsis.
+    fn truncate(text: &str, width: usize) -> String {
+        if text.width() <= width {
+            return text.to_string();
+        }
+        let mut truncated = String::new();
+        let mut truncated_width: usize = 0;
+        for grapheme in text.graphemes(true) {
+            let next_width = truncated_width.saturating_add(grapheme.width());
+            // Leave room for the ellipsis
+            if next_width >= width {
+                break;
+            }
+            truncated.push_str(grapheme);
+            truncated_width = next_width;
+        }
+        if width > 0 {
+            truncated.push('…');
+        }
+        truncated
+    }
 }
 
 impl UIComponent for StatusBar {
@@ -32,28 +106,56 @@ impl UIComponent for StatusBar {
         self.size = size;
     }
     fn draw(&mut self, origin_row: usize) -> Result<(), Error> {
-        //Assemble the first part of the status bar
-        let line_count = self.current_status.line_count_to_string();
-        let modified_indicator = self.current_status.modified_indicator_to_string();
-
-        let beginning = format!(
-            "{} - {line_count} {modified_indicator}",
-            self.current_status.file_name
-        );
-
-        // Assemble the whole status bar, with the position indicator at the back
-        let position_indicator = self.current_status.position_indicator_to_string();
-        let remainder_len = self.size.width.saturating_sub(beginning.len());
-        let status = format!("{beginning}{position_indicator:>remainder_len$}");
-
-        //Only print out the status if it fits. Otherwise write out an empty string to ensure the row is cleared.
-        let to_print = if status.len() <= self.size.width {
-            status
-        } else {
-            String::new()
-        };
-        Terminal::print_inverted_row(origin_row, &to_print)?;
+        Self::print_bar(origin_row, &self.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocumentStatus, Size, StatusBar, UIComponent};
+
+    fn status_bar(width: usize, left: &str, right: &str) -> StatusBar {
+        let mut bar = StatusBar::default();
+        bar.set_format(left.parse().unwrap(), right.parse().unwrap());
+        bar.update_status(DocumentStatus {
+            total_lines: 120,
+            current_line_idx: 41,
+            file_name: String::from("main.rs"),
+            is_modified: true,
+            ..DocumentStatus::default()
+        });
+        bar.resize(Size { height: 1, width });
+        bar
+    }
+
+    #[test]
+    fn both_sides_fit_with_padding_between_them() {
+        let bar = status_bar(30, "{name} {modified}", "{line}:{col}");
+        assert_eq!(bar.text(), "main.rs (modified)        42:1");
+    }
+
+    #[test]
+    fn the_left_side_is_shortened_first() {
+        let bar = status_bar(16, "{name} - {lines} {modified}", "{position}");
+        assert_eq!(bar.text(), "main.rs … 42/120");
+        // Down to a single column and the ellipsis
+        let bar = status_bar(8, "{name}", "{position}");
+        assert_eq!(bar.text(), "… 42/120");
+    }
+
+    #[test]
+    fn the_right_side_is_cut_when_nothing_else_fits() {
+        assert_eq!(status_bar(7, "{name}", "{position}").text(), "42/120");
+        assert_eq!(status_bar(4, "{name}", "{position}").text(), "42/…");
+        assert_eq!(status_bar(0, "{name}", "{position}").text(), "");
+    }
 
-        Ok(())
+    #[test]
+    fn hints_replace_the_status() {
+        let mut bar = status_bar(12, "{name}", "{position}");
+        bar.set_hint(Some(String::from("src/ tests/")));
+        assert_eq!(bar.text(), "src/ tests/ ");
+        bar.set_hint(Some(String::from("src/ tests/ target/")));
+        assert_eq!(bar.text(), "src/ tests/…");
     }
 }
@@ -0,0 +1,175 @@
+use super::{FileType, Line, Location};
+
+enum LexState {
+    Code,
+    BlockComment,
+    Literal(char),
+}
+
+fn starts_with(graphemes: &[&str], token: &str) -> bool {
+    let mut graphemes = graphemes.iter();
+    token.chars().all(|character| {
+        graphemes.next().is_some_and(|grapheme| {
+            let mut chars = grapheme.chars();
+            chars.next() == Some(character) && chars.next().is_none()
+        })
+    })
+}
+
+fn single_char(grapheme: &str) -> Option<char> {
+    let mut chars = grapheme.chars();
+    let character = chars.next()?;
+    chars.next().is_none().then_some(character)
+}
+
+/// Whether the `'` at the start of `graphemes` opens a character literal, rather than a Rust lifetime or label.
+fn opens_char_literal(graphemes: &[&str], file_type: FileType) -> bool {
+    if file_type != FileType::Rust {
+        return true;
+    }
+    matches!(graphemes.get(1), Some(&"\\")) || matches!(graphemes.get(2), Some(&"'"))
+}
+
+/// Collects the brackets which are part of the code, skipping those in comments and literals.
+/// They are in the order they appear in the text.
+pub fn code_brackets(lines: &[Line], file_type: FileType) -> Vec<(Location, char)> {
+    let line_comment = file_type.line_comment();
+    let block_comment = file_type.block_comment();
+    let quotes = file_type.quotes();
+
+    let mut brackets = Vec::new();
+    let mut state = LexState::Code;
+    for (line_idx, line) in lines.iter().enumerate() {
+        let graphemes: Vec<&str> = line.graphemes().collect();
+        let mut grapheme_idx = 0;
+        while let Some(rest) = graphemes.get(grapheme_idx..).filter(|rest| !rest.is_empty()) {
+            let character = rest.first().copied().and_then(single_char);
+            let mut step = 1;
+            match state {
+                LexState::Code => {
+                    if line_comment.is_some_and(|comment| starts_with(rest, comment)) {
+                        break;
+                    }
+                    if let Some((start, _)) = block_comment.filter(|(start, _)| starts_with(rest, start)) {
+                        state = LexState::BlockComment;
+                        step = start.len();
+                    } else if let Some(character) = character {
+                        if quotes.contains(&character)
+                            && (character != '\'' || opens_char_literal(rest, file_type))
+                        {
+                            state = LexState::Literal(character);
+                        } else if FileType::bracket_counterpart(character).is_some() {
+                            brackets.push((
+                                Location {
+                                    grapheme_idx,
+                                    line_idx,
+                                },
+                                character,
+                            ));
+                        }
+                    }
+                }
+                LexState::BlockComment => {
+                    if let Some((_, end)) = block_comment.filter(|(_, end)| starts_with(rest, end)) {
+                        state = LexState::Code;
+                        step = end.len();
+                    }
+                }
+                LexState::Literal(quote) => {
+                    if character == Some('\\') {
+                        step = 2;
+                    } else if character == Some(quote) {
+                        state = LexState::Code;
+                    }
+                }
+            }
+            grapheme_idx = grapheme_idx.saturating_add(step);
+        }
+        if let LexState::Literal(quote) = state {
+            if !file_type.multiline_quotes().contains(&quote) {
+                state = LexState::Code;
+            }
+        }
+    }
+    brackets
+}
+
+/// Finds the bracket at `at`, or right before it, among `brackets` as collected by `code_brackets`,
+/// and the bracket matching it. Returns `None` if there is no bracket there, or if it is unbalanced.
+pub fn bracket_pair(brackets: &[(Location, char)], at: Location) -> Option<(Location, Location)> {
+    let position = |location: Location| {
+        brackets
+            .binary_search_by(|(bracket_location, _)| bracket_location.cmp(&location))
+            .ok()
+    };
+    let before = at
+        .grapheme_idx
+        .checked_sub(1)
+        .map(|grapheme_idx| Location {
+            grapheme_idx,
+            line_idx: at.line_idx,
+        });
+    let idx = position(at).or_else(|| before.and_then(position))?;
+    let (bracket_location, bracket) = *brackets.get(idx)?;
+    let (counterpart, is_opening) = FileType::bracket_counterpart(bracket)?;
+
+    let mut depth: usize = 0;
+    let mut matching = |(location, character): &(Location, char)| {
+        if *character == bracket {
+            depth = depth.saturating_add(1);
+        } else if *character == counterpart {
+            if depth == 0 {
+                return Some(*location);
+            }
+            depth = depth.saturating_sub(1);
+        }
+        None
+    };
+    let found = if is_opening {
+        brackets.iter().skip(idx.saturating_add(1)).find_map(&mut matching)
+    } else {
+        brackets.iter().take(idx).rev().find_map(&mut matching)
+    }?;
+    Some((bracket_location, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bracket_pair, code_brackets, FileType, Line, Location};
+
+    fn location(line_idx: usize, grapheme_idx: usize) -> Location {
+        Location {
+            grapheme_idx,
+            line_idx,
+        }
+    }
+
+    fn pair(text: &[&str], at: Location) -> Option<(Location, Location)> {
+        let lines: Vec<Line> = text.iter().map(|line| Line::from(line)).collect();
+        bracket_pair(&code_brackets(&lines, FileType::Rust), at)
+    }
+
+    #[test]
+    fn brackets_in_comments_and_literals_are_skipped() {
+        let text = ["fn f() { // }", "    \"{\" /* ) */ '}'", "}"];
+        assert_eq!(pair(&text, location(0, 7)), Some((location(0, 7), location(2, 0))));
+        assert_eq!(pair(&text, location(2, 0)), Some((location(2, 0), location(0, 7))));
+        assert_eq!(pair(&text, location(0, 12)), None);
+        assert_eq!(pair(&text, location(1, 6)), None);
+    }
+
+    #[test]
+    fn the_bracket_right_before_the_caret_is_used() {
+        let text = ["a(b[c]d)"];
+        assert_eq!(pair(&text, location(0, 2)), Some((location(0, 1), location(0, 7))));
+        assert_eq!(pair(&text, location(0, 6)), Some((location(0, 5), location(0, 3))));
+        assert_eq!(pair(&text, location(0, 0)), None);
+    }
+
+    #[test]
+    fn unbalanced_brackets_have_no_pair() {
+        assert_eq!(pair(&["((x)"], location(0, 0)), None);
+        assert_eq!(pair(&["(x))"], location(0, 3)), None);
+        assert_eq!(pair(&["(]"], location(0, 0)), None);
+    }
+}
@@ -1,29 +1,54 @@
+use super::brackets;
+use super::GotoTarget;
 use super::FileInfo;
+use super::FileType;
+use super::LineEnding;
 use super::Line;
 use super::Location;
-use std::fs::{read_to_string, File};
+use std::cmp::min;
+use std::fs::{read_to_string, File, OpenOptions};
 use std::io::Error;
 use std::io::Write;
+use std::ops::Range;
 
 #[derive(Default)]
 pub struct Buffer {
     pub lines: Vec<Line>,
     pub file_info: FileInfo,
     pub dirty: bool,
+    pub read_only: bool,
+    pub line_ending: LineEnding,
+    /// Counts the changes to the lines, so that what is derived from them can tell when it is stale
+    revision: usize,
 }
 
 impl Buffer {
     pub fn load(file_name: &str) -> Result<Self, Error> {
         let contents = read_to_string(file_name)?;
+        let mut buffer = Self::from_contents(&contents);
+        buffer.file_info = FileInfo::from(file_name);
+        buffer.read_only = !is_writable(file_name);
+        Ok(buffer)
+    }
+
+    pub fn new_file(file_name: &str) -> Self {
+        Self {
+            file_info: FileInfo::from(file_name),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an unnamed buffer holding `contents`.
+    pub fn from_contents(contents: &str) -> Self {
         let mut lines = Vec::new();
         for value in contents.lines() {
             lines.push(Line::from(value));
         }
-        Ok(Self {
+        Self {
             lines,
-            file_info: FileInfo::from(file_name),
-            dirty: false,
-        })
+            line_ending: LineEnding::detect(contents),
+            ..Self::default()
+        }
     }
 
     pub fn search_forward(&self, query: &str, from: Location) -> Option<Location> {
@@ -88,7 +113,7 @@ impl Buffer {
         if let Some(file_path) = &file_info.get_path() {
             let mut file = File::create(file_path)?;
             for line in &self.lines {
-                writeln!(file, "{line}")?;
+                write!(file, "{line}{}", self.line_ending.as_str())?;
             }
         } else {
             #[cfg(debug_assertions)]
@@ -102,6 +127,8 @@ impl Buffer {
         let file_info = FileInfo::from(file_name);
         self.save_to_file(&file_info)?;
         self.file_info = file_info;
+        // The file type may have changed, and with it what counts as code
+        self.revision = self.revision.wrapping_add(1);
         self.dirty = false;
         Ok(())
     }
@@ -112,6 +139,89 @@ impl Buffer {
         Ok(())
     }
 
+    /// Resolves `target` to a location in this buffer. Relative targets count from `from`.
+    pub fn locate(&self, target: GotoTarget, from: Location) -> Result<Location, String> {
+        let height = self.height().max(1);
+        let current_line = from.line_idx.saturating_add(1);
+        let (line, col) = match target {
+            GotoTarget::Line { line, col } => (line, col),
+            GotoTarget::Forward(lines) => (current_line.saturating_add(lines), None),
+            GotoTarget::Backward(lines) => {
+                if lines >= current_line {
+                    return Err(format!("Cannot go back {lines} lines from line {current_line}"));
+                }
+                (current_line.saturating_sub(lines), None)
+            }
+            GotoTarget::Percent(percent) => {
+                if percent > 100 {
+                    return Err(format!("Percentage out of range (0-100): {percent}"));
+                }
+                let line_idx = height
+                    .saturating_sub(1)
+                    .saturating_mul(percent)
+                    .checked_div(100)
+                    .unwrap_or(0);
+                (line_idx.saturating_add(1), None)
+            }
+        };
+        if line == 0 || line > height {
+            return Err(format!("Line out of range (1-{height}): {line}"));
+        }
+        let line_idx = line.saturating_sub(1);
+        let grapheme_count = self.lines.get(line_idx).map_or(0, Line::grapheme_count);
+        let grapheme_idx = match col {
+            Some(col) if col == 0 || col > grapheme_count.saturating_add(1) => {
+                return Err(format!(
+                    "Column out of range (1-{}): {col}",
+                    grapheme_count.saturating_add(1)
+                ));
+            }
+            Some(col) => col.saturating_sub(1),
+            None => 0,
+        };
+        Ok(Location {
+            grapheme_idx,
+            line_idx,
+        })
+    }
+
+    /// Counts the changes to the lines so far.
+    pub const fn revision(&self) -> usize {
+        self.revision
+    }
+
+    fn mark_modified(&mut self) {
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// Adds `line` at the end of generated contents, without marking the buffer modified.
+    pub fn append_line(&mut self, line: Line) {
+        self.lines.push(line);
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// Returns the brackets which are part of the code, in the order they appear in the text.
+    pub fn code_brackets(&self) -> Vec<(Location, char)> {
+        brackets::code_brackets(&self.lines, self.file_info.get_file_type())
+    }
+
+    /// Removes trailing spaces and tabs from every line. Returns the number of changed lines.
+    pub fn strip_trailing_whitespace(&mut self) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        let stripped = self
+            .lines
+            .iter_mut()
+            .filter_map(|line| line.trim_trailing_whitespace().then_some(()))
+            .count();
+        if stripped > 0 {
+            self.mark_modified();
+        }
+        stripped
+    }
+
     pub fn is_empty(&self) -> bool {
         self.lines.is_empty()
     }
@@ -123,15 +233,110 @@ impl Buffer {
     }
     pub fn insert_char(&mut self, character: char, at: Location) {
         debug_assert!(at.line_idx <= self.height());
+        if self.read_only {
+            return;
+        }
         if at.line_idx == self.height() {
             self.lines.push(Line::from(&character.to_string()));
-            self.dirty = true;
+            self.mark_modified();
         } else if let Some(line) = self.lines.get_mut(at.line_idx) {
             line.insert_char(character, at.grapheme_idx);
-            self.dirty = true;
+            self.mark_modified();
         }
     }
+    /// Inserts `text`, which may span multiple lines, at `at` in one go.
+    /// Returns the `Location` right behind the inserted text.
+    pub fn insert_str(&mut self, text: &str, at: Location) -> Location {
+        debug_assert!(at.line_idx <= self.height());
+        if text.is_empty() || self.read_only {
+            return at;
+        }
+        if at.line_idx == self.height() {
+            self.lines.push(Line::default());
+        }
+        let Some(line) = self.lines.get_mut(at.line_idx) else {
+            return at;
+        };
+        let remainder = line.split(at.grapheme_idx);
+
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let mut pieces = text.split('\n');
+        if let Some(first) = pieces.next() {
+            line.append(&Line::from(first));
+        }
+        let mut line_idx = at.line_idx;
+        for piece in pieces {
+            line_idx = line_idx.saturating_add(1);
+            self.lines.insert(line_idx, Line::from(piece));
+        }
+
+        let mut end = Location {
+            grapheme_idx: 0,
+            line_idx,
+        };
+        if let Some(last_line) = self.lines.get_mut(line_idx) {
+            end.grapheme_idx = last_line.grapheme_count();
+            last_line.append(&remainder);
+        }
+        self.mark_modified();
+        end
+    }
+
+    /// Removes the text from `start` up to, but excluding, `end`, joining lines as needed.
+    pub fn delete_range(&mut self, start: Location, end: Location) {
+        if start >= end || start.line_idx >= self.height() || self.read_only {
+            return;
+        }
+        let height = self.height();
+        let start_line_len = self.lines.get(start.line_idx).map_or(0, Line::grapheme_count);
+        let remainder = self
+            .lines
+            .get(end.line_idx)
+            .map(|line| line.clone().split(end.grapheme_idx))
+            .unwrap_or_default();
+        if let Some(line) = self.lines.get_mut(start.line_idx) {
+            line.split(start.grapheme_idx);
+            line.append(&remainder);
+        }
+        let last_removed = min(end.line_idx, self.height().saturating_sub(1));
+        if last_removed > start.line_idx {
+            self.lines
+                .drain(start.line_idx.saturating_add(1)..=last_removed);
+        }
+        // A range past the end of the text, such as at the end of the last line, removes nothing
+        let start_line_changed = self
+            .lines
+            .get(start.line_idx)
+            .is_some_and(|line| line.grapheme_count() != start_line_len);
+        if start_line_changed || self.height() != height {
+            self.mark_modified();
+        }
+    }
+
+    /// Replaces the lines in `lines` with the lines of `text` in one go.
+    pub fn replace_lines(&mut self, lines: Range<usize>, text: &str) {
+        if self.read_only {
+            return;
+        }
+        let end = lines.end.min(self.height());
+        let start = lines.start.min(end);
+        self.lines.splice(start..end, text.lines().map(Line::from));
+        self.mark_modified();
+    }
+
+    /// Removes the line at `line_idx` along with its line break.
+    pub fn delete_line(&mut self, line_idx: usize) {
+        if line_idx >= self.height() || self.read_only {
+            return;
+        }
+        self.lines.remove(line_idx);
+        self.mark_modified();
+    }
+
     pub fn delete(&mut self, at: Location) {
+        if self.read_only {
+            return;
+        }
         if let Some(line) = self.lines.get(at.line_idx) {
             if at.grapheme_idx >= line.grapheme_count()
                 && self.height() > at.line_idx.saturating_add(1)
@@ -140,23 +345,289 @@ impl Buffer {
                 // clippy::indexing_slicing: We checked for existence of this line in the surrounding if statment
                 #[allow(clippy::indexing_slicing)]
                 self.lines[at.line_idx].append(&next_line);
-                self.dirty = true;
+                self.mark_modified();
             } else if at.grapheme_idx < line.grapheme_count() {
                 // clippy::indexing_slicing: We checked for existence of this line in the surrounding if statment
                 #[allow(clippy::indexing_slicing)]
                 self.lines[at.line_idx].delete(at.grapheme_idx);
-                self.dirty = true;
+                self.mark_modified();
             }
         }
     }
-    pub fn insert_newline(&mut self, at: Location) {
-        if at.line_idx == self.height() {
-            self.lines.push(Line::default());
-            self.dirty = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_idx) {
-            let new = line.split(at.grapheme_idx);
-            self.lines.insert(at.line_idx.saturating_add(1), new);
-            self.dirty = true;
+    /// Splits the line at `at` and returns the `Location` the caret belongs to afterwards.
+    /// With `auto_indent`, the new line starts with the indentation of the split line,
+    /// one level deeper if the split line ends with one of the file type's openers.
+    pub fn insert_newline(
+        &mut self,
+        at: Location,
+        auto_indent: bool,
+        indent_width: usize,
+    ) -> Location {
+        if self.read_only {
+            return at;
         }
+        let next_line_idx = at.line_idx.saturating_add(1);
+        let file_type = self.file_info.get_file_type();
+        let Some(line) = self.lines.get_mut(at.line_idx) else {
+            if at.line_idx == self.height() {
+                self.lines.push(Line::default());
+                self.mark_modified();
+            }
+            return Location {
+                grapheme_idx: 0,
+                line_idx: next_line_idx,
+            };
+        };
+        let remainder = line.split(at.grapheme_idx);
+        // Not `mark_modified`, as `line` is still borrowed
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+        if !auto_indent {
+            self.lines.insert(next_line_idx, remainder);
+            return Location {
+                grapheme_idx: 0,
+                line_idx: next_line_idx,
+            };
+        }
+
+        let mut indent = line.leading_whitespace().to_string();
+        let mut remainder = remainder.trim_start();
+        let mut closing_line = None;
+        if let Some(opener) = line
+            .trim_end()
+            .chars()
+            .last()
+            .filter(|last| file_type.indent_openers().contains(last))
+        {
+            // Put a closing bracket directly behind the caret onto its own line, e.g. when splitting `{}`
+            if let Some(closer) = FileType::closing_bracket(opener) {
+                if remainder.starts_with(closer) {
+                    closing_line = Some(Line::from(&format!("{indent}{remainder}")));
+                    remainder = "";
+                }
+            }
+            indent.push_str(&Self::indent_unit(&indent, indent_width));
+        }
+
+        let caret = Location {
+            grapheme_idx: indent.chars().count(),
+            line_idx: next_line_idx,
+        };
+        let new_line = Line::from(&format!("{indent}{remainder}"));
+        self.lines.insert(next_line_idx, new_line);
+        if let Some(closing_line) = closing_line {
+            self.lines
+                .insert(next_line_idx.saturating_add(1), closing_line);
+        }
+        caret
+    }
+
+    /// Removes one level of indentation from the start of the line.
+    /// Returns the number of removed graphemes.
+    pub fn dedent(&mut self, line_idx: usize, indent_width: usize) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        let Some(line) = self.lines.get_mut(line_idx) else {
+            return 0;
+        };
+        let leading_whitespace = line.leading_whitespace();
+        let leading_len = leading_whitespace.chars().count();
+        let removed = if leading_whitespace.ends_with('\t') {
+            1
+        } else {
+            leading_whitespace
+                .chars()
+                .rev()
+                .take_while(|character| *character == ' ')
+                .take(indent_width)
+                .count()
+        };
+        let start = leading_len.saturating_sub(removed);
+        for _ in 0..removed {
+            line.delete(start);
+        }
+        if removed > 0 {
+            self.mark_modified();
+        }
+        removed
+    }
+
+    fn indent_unit(existing_indent: &str, indent_width: usize) -> String {
+        if existing_indent.contains('\t') {
+            String::from("\t")
+        } else {
+            " ".repeat(indent_width)
+        }
+    }
+}
+
+/// Whether the file could be opened for writing, without modifying it.
+fn is_writable(file_name: &str) -> bool {
+    OpenOptions::new().append(true).open(file_name).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Buffer, FileInfo, FileType, GotoTarget, Location};
+
+    fn location(line_idx: usize, grapheme_idx: usize) -> Location {
+        Location {
+            grapheme_idx,
+            line_idx,
+        }
+    }
+
+    fn text(buffer: &Buffer) -> Vec<String> {
+        buffer.lines.iter().map(ToString::to_string).collect()
+    }
+
+    /// A buffer of the file type `file_name` implies, holding `contents`.
+    fn buffer(file_name: &str, contents: &str) -> Buffer {
+        let mut buffer = Buffer::from_contents(contents);
+        buffer.file_info = FileInfo::from(file_name);
+        buffer
+    }
+
+    #[test]
+    fn goto_targets_are_located() {
+        let buffer = Buffer::from_contents("one\ntwo\nthree\nfour\nfive\n");
+        let from = location(2, 3);
+        let locate = |target: &str| buffer.locate(target.parse::<GotoTarget>().unwrap(), from);
+        assert_eq!(locate("1"), Ok(location(0, 0)));
+        assert_eq!(locate("3:6"), Ok(location(2, 5)));
+        assert_eq!(locate("+2"), Ok(location(4, 0)));
+        assert_eq!(locate("-2"), Ok(location(0, 0)));
+        assert_eq!(locate("+0"), Ok(location(2, 0)));
+        assert_eq!(locate("%0"), Ok(location(0, 0)));
+        assert_eq!(locate("%50"), Ok(location(2, 0)));
+        assert_eq!(locate("%100"), Ok(location(4, 0)));
+    }
+
+    #[test]
+    fn goto_targets_out_of_range_are_refused() {
+        let buffer = Buffer::from_contents("one\ntwo\nthree\nfour\nfive\n");
+        let from = location(2, 0);
+        let locate = |target: &str| buffer.locate(target.parse::<GotoTarget>().unwrap(), from);
+        assert_eq!(locate("0"), Err(String::from("Line out of range (1-5): 0")));
+        assert_eq!(locate("6"), Err(String::from("Line out of range (1-5): 6")));
+        assert_eq!(locate("+3"), Err(String::from("Line out of range (1-5): 6")));
+        assert_eq!(locate("-3"), Err(String::from("Cannot go back 3 lines from line 3")));
+        assert_eq!(locate("%101"), Err(String::from("Percentage out of range (0-100): 101")));
+        assert_eq!(locate("2:0"), Err(String::from("Column out of range (1-4): 0")));
+        assert_eq!(locate("2:5"), Err(String::from("Column out of range (1-4): 5")));
+        assert_eq!(locate(&format!("+{}", usize::MAX)), Err(format!("Line out of range (1-5): {}", usize::MAX)));
+    }
+
+    #[test]
+    fn an_empty_buffer_has_a_first_line() {
+        let buffer = Buffer::default();
+        assert_eq!(buffer.locate(GotoTarget::Line { line: 1, col: Some(1) }, location(0, 0)), Ok(location(0, 0)));
+        assert_eq!(buffer.locate(GotoTarget::Percent(100), location(0, 0)), Ok(location(0, 0)));
+    }
+
+    #[test]
+    fn new_lines_keep_the_indentation() {
+        let mut buffer = buffer("main.rs", "    let x = 1;\n\tfoo bar");
+        assert_eq!(buffer.insert_newline(location(0, 14), true, 4), location(1, 4));
+        assert_eq!(buffer.insert_newline(location(2, 4), true, 4), location(3, 1));
+        assert_eq!(text(&buffer), ["    let x = 1;", "    ", "\tfoo", "\tbar"]);
+        assert!(buffer.dirty);
+    }
+
+    #[test]
+    fn new_lines_after_an_opening_bracket_are_indented_deeper() {
+        let mut buffer = buffer("main.rs", "fn main() {\n\tif x {}\n    call(");
+        assert_eq!(buffer.insert_newline(location(2, 9), true, 4), location(3, 8));
+        assert_eq!(buffer.insert_newline(location(1, 7), true, 4), location(2, 2));
+        assert_eq!(buffer.insert_newline(location(0, 11), true, 2), location(1, 2));
+        assert_eq!(
+            text(&buffer),
+            ["fn main() {", "  ", "\tif x {", "\t\t", "\t}", "    call(", "        "]
+        );
+    }
+
+    #[test]
+    fn openers_depend_on_the_file_type() {
+        let mut python = buffer("main.py", "if x:");
+        assert_eq!(python.insert_newline(location(0, 5), true, 4), location(1, 4));
+        let mut text_file = buffer("notes.txt", "  list {");
+        assert_eq!(text_file.insert_newline(location(0, 8), true, 4), location(1, 2));
+        let mut yaml = buffer("config.yaml", "key: {");
+        assert_eq!(yaml.insert_newline(location(0, 6), true, 2), location(1, 0));
+    }
+
+    #[test]
+    fn new_lines_are_not_indented_without_auto_indent() {
+        let mut buffer = buffer("main.rs", "    if x {}");
+        assert_eq!(buffer.insert_newline(location(0, 10), false, 4), location(1, 0));
+        assert_eq!(text(&buffer), ["    if x {", "}"]);
+    }
+
+    #[test]
+    fn closing_brackets_dedent() {
+        assert!(FileType::Rust.dedents_on('}'));
+        assert!(FileType::Python.dedents_on(']'));
+        assert!(!FileType::Rust.dedents_on('{'));
+        assert!(!FileType::Yaml.dedents_on('}'));
+        assert!(!FileType::Text.dedents_on(')'));
+
+        let mut buffer = buffer("main.rs", "        \n      \n  \n\t\t\n\t  \nx");
+        let removed: Vec<usize> = (0..6).map(|line_idx| buffer.dedent(line_idx, 4)).collect();
+        assert_eq!(removed, [4, 4, 2, 1, 2, 0]);
+        assert_eq!(text(&buffer), ["    ", "  ", "", "\t", "\t", "x"]);
+    }
+
+    #[test]
+    fn nothing_to_dedent_leaves_the_buffer_unmodified() {
+        let mut buffer = buffer("main.rs", "x");
+        assert_eq!(buffer.dedent(0, 4), 0);
+        assert_eq!(buffer.dedent(1, 4), 0);
+        assert!(!buffer.dirty);
+    }
+
+    #[test]
+    fn pasted_lines_are_inserted_in_one_go() {
+        let mut buffer = Buffer::from_contents("fn main() {}");
+        let end = buffer.insert_str("\n    one();\n    two();\n", location(0, 11));
+        assert_eq!(end, location(3, 0));
+        assert_eq!(text(&buffer), ["fn main() {", "    one();", "    two();", "}"]);
+        assert!(buffer.dirty);
+    }
+
+    #[test]
+    fn pasting_in_the_middle_of_a_line_keeps_the_rest_behind_the_paste() {
+        let mut buffer = Buffer::from_contents("hello world");
+        assert_eq!(buffer.insert_str("big ", location(0, 6)), location(0, 10));
+        assert_eq!(text(&buffer), ["hello big world"]);
+        assert_eq!(buffer.insert_str("ö\nä", location(0, 5)), location(1, 1));
+        assert_eq!(text(&buffer), ["helloö", "ä big world"]);
+    }
+
+    #[test]
+    fn pasted_line_breaks_are_normalised() {
+        let mut buffer = Buffer::from_contents("ab");
+        assert_eq!(buffer.insert_str("1\r\n2\r3\n", location(0, 1)), location(3, 0));
+        assert_eq!(text(&buffer), ["a1", "2", "3", "b"]);
+    }
+
+    #[test]
+    fn pasting_behind_the_last_line_adds_a_line() {
+        let mut buffer = Buffer::default();
+        assert_eq!(buffer.insert_str("x\ny", location(0, 0)), location(1, 1));
+        assert_eq!(text(&buffer), ["x", "y"]);
+        assert_eq!(buffer.insert_str("z", location(2, 0)), location(2, 1));
+        assert_eq!(text(&buffer), ["x", "y", "z"]);
+    }
+
+    #[test]
+    fn nothing_is_pasted_into_read_only_buffers() {
+        let mut buffer = Buffer::from_contents("x");
+        assert_eq!(buffer.insert_str("", location(0, 0)), location(0, 0));
+        assert!(!buffer.dirty);
+        buffer.read_only = true;
+        assert_eq!(buffer.insert_str("y", location(0, 1)), location(0, 1));
+        assert_eq!(text(&buffer), ["x"]);
+        assert!(!buffer.dirty);
     }
 }
@@ -0,0 +1,27 @@
+use std::{fs::read_dir, io::Error, path::Path};
+
+/// Lists `dir` for the directory browser, one entry per line: `../` first, then the directories
+/// with a trailing `/`, then the files, each sorted by name.
+pub fn list_directory(dir: &Path) -> Result<Vec<String>, Error> {
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.path().is_dir() {
+            directories.push(format!("{name}/"));
+        } else {
+            files.push(name);
+        }
+    }
+    directories.sort();
+    files.sort();
+
+    let mut entries = Vec::new();
+    if dir.parent().is_some() {
+        entries.push(String::from("../"));
+    }
+    entries.append(&mut directories);
+    entries.append(&mut files);
+    Ok(entries)
+}
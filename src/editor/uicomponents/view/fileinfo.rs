@@ -3,17 +3,38 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use super::FileType;
+
 #[derive(Default, Debug)]
 pub struct FileInfo {
     path: Option<PathBuf>,
+    /// Shown instead of a file name for buffers which are not backed by a file, such as the message history
+    scratch_name: Option<String>,
+    file_type: FileType,
 }
 
 impl FileInfo {
     pub fn from(file_name: &str) -> Self {
+        let path = PathBuf::from(file_name);
+        let file_type = FileType::from_path(&path);
+        Self {
+            path: Some(path),
+            scratch_name: None,
+            file_type,
+        }
+    }
+    pub fn scratch(name: &str) -> Self {
         Self {
-            path: Some(PathBuf::from(file_name)),
+            scratch_name: Some(name.to_string()),
+            ..Self::default()
         }
     }
+    pub const fn is_scratch(&self) -> bool {
+        self.scratch_name.is_some()
+    }
+    pub const fn get_file_type(&self) -> FileType {
+        self.file_type
+    }
     pub fn get_path(&self) -> Option<&Path> {
         self.path.as_deref()
     }
@@ -28,6 +49,7 @@ impl Display for FileInfo {
             .get_path()
             .and_then(|path| path.file_name())
             .and_then(|name| name.to_str())
+            .or(self.scratch_name.as_deref())
             .unwrap_or("[No Name]");
         write!(formatter, "{name}")
     }
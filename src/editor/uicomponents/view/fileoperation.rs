@@ -0,0 +1,46 @@
+use std::{
+    fs::{create_dir, remove_dir_all, remove_file, rename, File},
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+/// A change to the entries of a directory, entered in the directory browser.
+pub enum FileOperation {
+    CreateFile(String),
+    CreateDirectory(String),
+    Rename { entry: PathBuf, to: String },
+    /// Deletes a file, or a directory with everything in it
+    Delete(PathBuf),
+}
+
+impl FileOperation {
+    /// Carries out the operation in `dir`. Returns a message saying what was done and the name of
+    /// the entry to select afterwards, which is empty if there is none.
+    pub fn run(&self, dir: &Path) -> Result<(String, String), Error> {
+        match self {
+            Self::CreateFile(name) => File::create_new(dir.join(name))
+                .map(|_| (format!("Created {name}."), name.clone())),
+            Self::CreateDirectory(name) => create_dir(dir.join(name))
+                .map(|()| (format!("Created {name}/."), name.clone())),
+            Self::Rename { entry, to } => rename(entry, dir.join(to)).map(|()| {
+                (format!("Renamed {} to {to}.", entry_name(entry)), to.clone())
+            }),
+            Self::Delete(entry) => {
+                if entry.is_dir() {
+                    remove_dir_all(entry)
+                } else {
+                    remove_file(entry)
+                }
+                .map(|()| (format!("Deleted {}.", entry_name(entry)), String::new()))
+            }
+        }
+    }
+}
+
+/// The name `entry` is listed under in its directory.
+pub fn entry_name(entry: &Path) -> String {
+    entry
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,159 @@
+use std::{
+    fmt::{self, Display},
+    path::Path,
+};
+
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FileType {
+    Rust,
+    C,
+    Go,
+    Java,
+    JavaScript,
+    Python,
+    Json,
+    Toml,
+    Yaml,
+    Shell,
+    Markdown,
+    #[default]
+    Text,
+}
+
+impl FileType {
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        match extension {
+            "rs" => Self::Rust,
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" => Self::C,
+            "go" => Self::Go,
+            "java" => Self::Java,
+            "js" | "jsx" | "mjs" | "ts" | "tsx" => Self::JavaScript,
+            "py" | "pyi" => Self::Python,
+            "json" => Self::Json,
+            "toml" => Self::Toml,
+            "yml" | "yaml" => Self::Yaml,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            "md" | "markdown" => Self::Markdown,
+            _ => Self::Text,
+        }
+    }
+
+    /// Characters which, when ending a line, cause the next line to be indented one level deeper.
+    pub const fn indent_openers(self) -> &'static [char] {
+        match self {
+            Self::Rust | Self::C | Self::Go | Self::Java | Self::JavaScript | Self::Json => {
+                &['{', '(', '[']
+            }
+            Self::Python => &['{', '(', '[', ':'],
+            Self::Yaml => &[':'],
+            Self::Toml | Self::Shell | Self::Markdown | Self::Text => &[],
+        }
+    }
+
+    /// Returns the character closing `opener`, if `opener` is a bracket.
+    pub const fn closing_bracket(opener: char) -> Option<char> {
+        match opener {
+            '{' => Some('}'),
+            '(' => Some(')'),
+            '[' => Some(']'),
+            _ => None,
+        }
+    }
+
+    /// Returns the bracket which pairs with `bracket`, along with whether `bracket` is the opening one.
+    pub const fn bracket_counterpart(bracket: char) -> Option<(char, bool)> {
+        match bracket {
+            '{' => Some(('}', true)),
+            '(' => Some((')', true)),
+            '[' => Some((']', true)),
+            '}' => Some(('{', false)),
+            ')' => Some(('(', false)),
+            ']' => Some(('[', false)),
+            _ => None,
+        }
+    }
+
+    pub const fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::C | Self::Go | Self::Java | Self::JavaScript => Some("//"),
+            Self::Python | Self::Toml | Self::Yaml | Self::Shell => Some("#"),
+            Self::Json | Self::Markdown | Self::Text => None,
+        }
+    }
+
+    pub const fn block_comment(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Self::Rust | Self::C | Self::Go | Self::Java | Self::JavaScript => Some(("/*", "*/")),
+            Self::Python
+            | Self::Json
+            | Self::Toml
+            | Self::Yaml
+            | Self::Shell
+            | Self::Markdown
+            | Self::Text => None,
+        }
+    }
+
+    /// Characters which start and end string or character literals.
+    pub const fn quotes(self) -> &'static [char] {
+        match self {
+            Self::Rust
+            | Self::C
+            | Self::Java
+            | Self::Python
+            | Self::Toml
+            | Self::Yaml
+            | Self::Shell => &['"', '\''],
+            Self::Go | Self::JavaScript => &['"', '\'', '`'],
+            Self::Json => &['"'],
+            Self::Markdown | Self::Text => &[],
+        }
+    }
+
+    /// Quotes whose literals may continue on the next line.
+    pub const fn multiline_quotes(self) -> &'static [char] {
+        match self {
+            Self::Rust | Self::Shell => &['"'],
+            Self::Go | Self::JavaScript => &['`'],
+            Self::C
+            | Self::Java
+            | Self::Python
+            | Self::Json
+            | Self::Toml
+            | Self::Yaml
+            | Self::Markdown
+            | Self::Text => &[],
+        }
+    }
+
+    /// Whether typing `character` on an otherwise blank line should remove one level of indentation.
+    pub fn dedents_on(self, character: char) -> bool {
+        self.indent_openers()
+            .iter()
+            .any(|opener| Self::closing_bracket(*opener) == Some(character))
+    }
+}
+
+impl Display for FileType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Rust => "Rust",
+            Self::C => "C",
+            Self::Go => "Go",
+            Self::Java => "Java",
+            Self::JavaScript => "JavaScript",
+            Self::Python => "Python",
+            Self::Json => "JSON",
+            Self::Toml => "TOML",
+            Self::Yaml => "YAML",
+            Self::Shell => "Shell",
+            Self::Markdown => "Markdown",
+            Self::Text => "Text",
+        };
+        write!(formatter, "{name}")
+    }
+}
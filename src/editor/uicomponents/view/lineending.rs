@@ -0,0 +1,35 @@
+use std::fmt::{self, Display};
+
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Picks the line ending used by the first line of `contents`.
+    pub fn detect(contents: &str) -> Self {
+        match contents.find('\n') {
+            Some(idx) if contents.get(..idx).is_some_and(|line| line.ends_with('\r')) => Self::CrLf,
+            _ => Self::Lf,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        };
+        write!(formatter, "{name}")
+    }
+}
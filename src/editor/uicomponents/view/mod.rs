@@ -1,15 +1,30 @@
-use std::{cmp::min, io::Error};
+use std::{
+    cmp::{max, min},
+    io::Error,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 use super::super::{
-    DocumentStatus, Line, Terminal, NAME, VERSION,
+    AnnotatedString, AnnotationType, DocumentStatus, GotoTarget, Line, ProjectMatch, Settings, Terminal, NAME,
+    VERSION,
 };
 use super::UIComponent;
+mod brackets;
 mod buffer;
-use buffer::Buffer;
+mod directorylisting;
+use directorylisting::list_directory;
+mod fileoperation;
+pub use fileoperation::{entry_name, FileOperation};
+pub use buffer::Buffer;
 mod searchdirection;
 use searchdirection::SearchDirection;
 mod fileinfo;
 use fileinfo::FileInfo;
+mod filetype;
+use filetype::FileType;
+mod lineending;
+use lineending::LineEnding;
 mod searchinfo;
 use searchinfo::SearchInfo;
 use super::super::prelude::*;
@@ -21,19 +36,71 @@ pub struct View {
     size: Size,
     text_location: Location,
     scroll_offset: Position,
+    drawn_scroll_offset: Option<Position>,
     search_info: Option<SearchInfo>,
+    selection_anchor: Option<Location>,
+    bracket_pair: Option<(Location, Location)>,
+    /// The caret location and buffer revision `bracket_pair` was looked for at
+    bracket_pair_key: Option<(Location, usize)>,
+    /// The brackets in the code and the buffer revision they were collected at
+    code_brackets: Option<(usize, Vec<(Location, char)>)>,
+    /// The directory shown, if this view is a directory browser
+    directory: Option<PathBuf>,
+    /// The match shown on each line, if this view lists the results of a project search. The
+    /// ranges of the matches are relative to the whole line.
+    project_matches: Option<Vec<ProjectMatch>>,
+    /// Whether closing the scratch view shown in front of this view returns to it
+    is_scratch_origin: bool,
+    settings: Settings,
 }
 
 impl View {
     pub fn get_status(&self) -> DocumentStatus {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let (selected_graphemes, selected_lines) = self.selection_size();
         DocumentStatus {
             total_lines: self.buffer.height(),
-            current_line_idx: self.text_location.line_idx,
+            current_line_idx: line_idx,
+            current_grapheme_idx: grapheme_idx,
+            current_col: self
+                .buffer
+                .lines
+                .get(line_idx)
+                .map_or(0, |line| line.width_until(grapheme_idx, self.settings.tab_width)),
+            selected_graphemes,
+            selected_lines,
             file_name: format!("{}", self.buffer.file_info),
             is_modified: self.buffer.dirty,
+            is_read_only: self.buffer.read_only,
+            file_type: self.buffer.file_info.get_file_type().to_string(),
+            // Files are read with `read_to_string`, so anything which is loaded is UTF-8
+            encoding: String::from("UTF-8"),
+            line_ending: self.buffer.line_ending.to_string(),
+            mode: String::new(),
         }
     }
 
+    /// Returns the number of selected graphemes, counting line breaks as one, and the number of lines they span.
+    fn selection_size(&self) -> (usize, usize) {
+        let Some((start, end)) = self.selection() else {
+            return (0, 0);
+        };
+        let graphemes = (start.line_idx..=end.line_idx)
+            .filter_map(|line_idx| self.selection_on_line(line_idx))
+            .map(|range| range.len())
+            .sum::<usize>()
+            .saturating_add(end.line_idx.saturating_sub(start.line_idx));
+        (graphemes, end.line_idx.saturating_sub(start.line_idx).saturating_add(1))
+    }
+
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+        self.set_needs_redraw(true);
+    }
+
     pub const fn is_file_loaded(&self) -> bool {
         self.buffer.is_file_loaded()
     }
@@ -93,7 +160,7 @@ impl View {
         }) {
             self.text_location = location;
             self.center_text_location();
-        };
+        }
         self.set_needs_redraw(true);
     }
 
@@ -115,22 +182,268 @@ impl View {
 
     pub fn load(&mut self, file_name: &str) -> Result<(), Error> {
         let buffer = Buffer::load(file_name)?;
+        self.replace_buffer(buffer);
+        Ok(())
+    }
+
+    /// Replaces the buffer with an empty one, which will be saved as `file_name`.
+    pub fn new_file(&mut self, file_name: &str) {
+        self.replace_buffer(Buffer::new_file(file_name));
+    }
+
+    /// Replaces the buffer with an unnamed one holding `contents`.
+    pub fn load_contents(&mut self, contents: &str) {
+        self.replace_buffer(Buffer::from_contents(contents));
+    }
+
+    fn replace_buffer(&mut self, buffer: Buffer) {
         self.buffer = buffer;
+        // A new buffer starts counting its revisions over
+        self.bracket_pair_key = None;
+        self.code_brackets = None;
+        self.directory = None;
+        self.project_matches = None;
         self.set_needs_redraw(true);
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.buffer.file_info.get_path()
+    }
+
+    /// Turns the view into a browser listing the entries of `dir`.
+    pub fn open_directory(&mut self, dir: &Path) -> Result<(), Error> {
+        let dir = dir.canonicalize()?;
+        let entries = list_directory(&dir)?;
+        let name = dir.join("").display().to_string();
+        self.load_scratch(&name, &entries.join("\n"));
+        self.directory = Some(dir);
+        self.clear_selection();
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
         Ok(())
     }
 
+    /// Lists the directory again after it was changed, keeping the caret on the same line if possible.
+    pub fn refresh_directory(&mut self) -> Result<(), Error> {
+        let Some(dir) = self.directory.clone() else {
+            return Ok(());
+        };
+        let line_idx = self.text_location.line_idx;
+        self.open_directory(&dir)?;
+        self.text_location.line_idx = min(line_idx, self.buffer.height().saturating_sub(1));
+        self.scroll_text_location_into_view();
+        Ok(())
+    }
+
+    /// Carries out `operation` in the browsed directory and lists the directory again, with the
+    /// caret on the entry which was created or renamed. Returns a message saying what was done.
+    pub fn run_file_operation(&mut self, operation: &FileOperation) -> Result<String, String> {
+        let Some(dir) = self.directory.clone() else {
+            return Err(String::from("No directory is shown."));
+        };
+        let (message, selected) = operation
+            .run(&dir)
+            .map_err(|err| format!("File operation failed: {err}"))?;
+        self.refresh_directory()
+            .map_err(|err| format!("Could not list {}: {err}", dir.display()))?;
+        self.select_entry(&selected);
+        Ok(message)
+    }
+
+    /// Moves the caret of a directory browser to the entry called `name`, if it is listed.
+    pub fn select_entry(&mut self, name: &str) {
+        let line_idx = self.buffer.lines.iter().position(|line| {
+            let entry = line.to_string();
+            entry == name || entry.strip_suffix('/') == Some(name)
+        });
+        if let Some(line_idx) = line_idx {
+            self.text_location = Location {
+                line_idx,
+                grapheme_idx: 0,
+            };
+            self.scroll_text_location_into_view();
+        }
+    }
+
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    /// Returns the path of the entry under the caret in a directory browser.
+    pub fn selected_entry(&self) -> Option<PathBuf> {
+        let dir = self.directory.as_ref()?;
+        let name = self.buffer.lines.get(self.text_location.line_idx)?.to_string();
+        if name == "../" {
+            return dir.parent().map(Path::to_path_buf);
+        }
+        Some(dir.join(name.trim_end_matches('/')))
+    }
+
+    /// Replaces the buffer with a read-only one holding `contents`, which is not backed by a file.
+    pub fn load_scratch(&mut self, name: &str, contents: &str) {
+        self.load_contents(contents);
+        self.buffer.file_info = FileInfo::scratch(name);
+        self.buffer.read_only = true;
+    }
+
+    pub const fn is_scratch(&self) -> bool {
+        self.buffer.file_info.is_scratch()
+    }
+
+    pub const fn is_scratch_origin(&self) -> bool {
+        self.is_scratch_origin
+    }
+
+    pub fn set_scratch_origin(&mut self, is_scratch_origin: bool) {
+        self.is_scratch_origin = is_scratch_origin;
+    }
+
+    /// Whether the view can be dropped once another one is shown, as it is easily recreated.
+    pub const fn is_disposable(&self) -> bool {
+        self.is_scratch() && !self.is_project_search()
+    }
+
+    /// Turns the view into an empty list of project search results.
+    pub fn start_project_search(&mut self, name: &str) {
+        self.load_scratch(name, "");
+        self.project_matches = Some(Vec::new());
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
+    }
+
+    pub const fn is_project_search(&self) -> bool {
+        self.project_matches.is_some()
+    }
+
+    /// Adds a line `path:line: text` for `found` to the search results.
+    pub fn append_project_match(&mut self, found: ProjectMatch) {
+        let Some(project_matches) = self.project_matches.as_mut() else {
+            return;
+        };
+        let prefix = format!(
+            "{}:{}: ",
+            found.path.display(),
+            found.location.line_idx.saturating_add(1)
+        );
+        let prefix_len = Line::from(&prefix).grapheme_count();
+        self.buffer
+            .append_line(Line::from(&format!("{prefix}{}", found.text)));
+        let matches = found
+            .matches
+            .iter()
+            .map(|range| range.start.saturating_add(prefix_len)..range.end.saturating_add(prefix_len))
+            .collect();
+        project_matches.push(ProjectMatch { matches, ..found });
+        self.set_needs_redraw(true);
+    }
+
+    /// Returns the file and location of the search result under the caret.
+    pub fn selected_project_match(&self) -> Option<(&Path, Location)> {
+        let found = self
+            .project_matches
+            .as_ref()?
+            .get(self.text_location.line_idx)?;
+        Some((&found.path, found.location))
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.buffer.read_only = read_only;
+    }
+
+    pub const fn is_read_only(&self) -> bool {
+        self.buffer.read_only
+    }
+
+    /// Moves the caret to the first match of `query` in the buffer. Returns whether there was a match.
+    pub fn find_first(&mut self, query: &str) -> bool {
+        let Some(location) = self.buffer.search_forward(query, Location::default()) else {
+            return false;
+        };
+        self.clear_selection();
+        self.text_location = location;
+        self.center_text_location();
+        true
+    }
+
     pub fn save(&mut self) -> Result<(), Error> {
+        self.strip_on_save();
         self.buffer.save()
     }
 
     pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
+        self.strip_on_save();
         self.buffer.save_as(file_name)
     }
 
+    fn strip_on_save(&mut self) {
+        if self.settings.strip_trailing_whitespace_on_save {
+            self.strip_trailing_whitespace();
+        }
+    }
+
+    /// Returns the number of lines which had trailing whitespace.
+    pub fn strip_trailing_whitespace(&mut self) -> usize {
+        let stripped = self.buffer.strip_trailing_whitespace();
+        if stripped > 0 {
+            self.clear_selection();
+            let line_len = self
+                .buffer
+                .lines
+                .get(self.text_location.line_idx)
+                .map_or(0, Line::grapheme_count);
+            self.text_location.grapheme_idx = min(self.text_location.grapheme_idx, line_len);
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+        stripped
+    }
+
+    /// The lines the selection touches, or all lines if nothing is selected.
+    pub fn selected_lines(&self) -> Range<LineIdx> {
+        let height = self.buffer.height();
+        let Some((start, end)) = self.selection() else {
+            return 0..height;
+        };
+        // A selection which ends at the start of a line doesn't take that line along
+        let end_line_idx = if end.grapheme_idx == 0 && end.line_idx > start.line_idx {
+            end.line_idx
+        } else {
+            end.line_idx.saturating_add(1)
+        };
+        start.line_idx..end_line_idx.min(height)
+    }
+
+    /// Returns the text of `lines`, each followed by a line break.
+    pub fn lines_text(&self, lines: Range<LineIdx>) -> String {
+        let mut text = String::new();
+        for line in self.buffer.lines.get(lines).unwrap_or_default() {
+            text.push_str(&line.to_string());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Replaces `lines` with the lines of `text`, placing the caret at the start of the first of them.
+    pub fn replace_lines(&mut self, lines: Range<LineIdx>, text: &str) {
+        self.clear_selection();
+        let line_idx = lines.start;
+        self.buffer.replace_lines(lines, text);
+        self.text_location = Location {
+            grapheme_idx: 0,
+            line_idx: line_idx.min(self.buffer.height()),
+        };
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
     pub fn insert_newline(&mut self) {
-        self.buffer.insert_newline(self.text_location);
-        self.move_right();
+        self.clear_selection();
+        self.text_location = self.buffer.insert_newline(
+            self.text_location,
+            self.settings.auto_indent,
+            self.settings.indent_width,
+        );
+        self.scroll_text_location_into_view();
         self.set_needs_redraw(true);
     }
 
@@ -142,11 +455,30 @@ impl View {
     }
 
     pub fn delete(&mut self) {
+        self.clear_selection();
         self.buffer.delete(self.text_location);
         self.set_needs_redraw(true);
     }
 
     pub fn insert_char(&mut self, character: char) {
+        self.clear_selection();
+        if self.settings.auto_indent
+            && self
+                .buffer
+                .file_info
+                .get_file_type()
+                .dedents_on(character)
+            && self
+                .buffer
+                .lines
+                .get(self.text_location.line_idx)
+                .is_some_and(|line| line.trim().is_empty())
+        {
+            let removed = self
+                .buffer
+                .dedent(self.text_location.line_idx, self.settings.indent_width);
+            self.text_location.grapheme_idx = self.text_location.grapheme_idx.saturating_sub(removed);
+        }
         let old_len = self
             .buffer
             .lines
@@ -165,6 +497,26 @@ impl View {
         self.set_needs_redraw(true);
     }
 
+    /// Inserts a tab, or spaces up to the next tab stop if tabs are expanded.
+    pub fn insert_tab(&mut self) {
+        if !self.settings.expand_tabs {
+            self.insert_char('\t');
+            return;
+        }
+        let Position { col, .. } = self.text_location_to_position();
+        let tab_width = self.settings.tab_width;
+        let spaces = tab_width.saturating_sub(col.checked_rem(tab_width).unwrap_or(0));
+        self.insert_str(&" ".repeat(spaces.max(1)));
+    }
+
+    /// Inserts pasted text as a whole, placing the caret behind it.
+    pub fn insert_str(&mut self, text: &str) {
+        self.clear_selection();
+        self.text_location = self.buffer.insert_str(text, self.text_location);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
     fn render_line(at: usize, line_text: &str) -> Result<(), Error> {
         Terminal::print_row(at, line_text)
     }
@@ -235,6 +587,187 @@ impl View {
             .saturating_sub(self.scroll_offset)
     }
 
+    /// Translates a `Position` relative to the top left corner of the view into the `Location` of the text displayed there.
+    /// Positions behind the end of a line map to its end, positions below the text to the last line.
+    pub fn position_to_text_location(&self, position: Position) -> Location {
+        let line_idx = min(
+            position.row.saturating_add(self.scroll_offset.row),
+            self.buffer.height().saturating_sub(1),
+        );
+        let col = position.col.saturating_add(self.scroll_offset.col);
+        let grapheme_idx = self
+            .buffer
+            .lines
+            .get(line_idx)
+            .map_or(0, |line| line.grapheme_idx_at_col(col, self.settings.tab_width));
+        Location {
+            grapheme_idx,
+            line_idx,
+        }
+    }
+
+    /// Returns the selected range as `(start, end)`, with `start` before `end`.
+    pub fn selection(&self) -> Option<(Location, Location)> {
+        self.selection_anchor
+            .filter(|anchor| *anchor != self.text_location)
+            .map(|anchor| {
+                (
+                    min(anchor, self.text_location),
+                    max(anchor, self.text_location),
+                )
+            })
+    }
+
+    pub fn clear_selection(&mut self) {
+        if self.selection_anchor.take().is_some() {
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// Looks for a bracket pair around the caret, and redraws if it differs from the highlighted one.
+    pub fn update_bracket_pair(&mut self) {
+        let key = (self.text_location, self.buffer.revision());
+        if self.bracket_pair_key == Some(key) {
+            return;
+        }
+        self.bracket_pair_key = Some(key);
+        let bracket_pair = self.find_bracket_pair();
+        if bracket_pair != self.bracket_pair {
+            self.bracket_pair = bracket_pair;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    pub fn jump_to_matching_bracket(&mut self) -> bool {
+        let Some((_, matching)) = self.find_bracket_pair() else {
+            return false;
+        };
+        self.clear_selection();
+        self.text_location = matching;
+        self.scroll_text_location_into_view();
+        true
+    }
+
+    /// Returns the bracket at or right before the caret, along with its matching bracket.
+    fn find_bracket_pair(&mut self) -> Option<(Location, Location)> {
+        // Collecting the brackets lexes the whole buffer, so only do it again after the text changed
+        let revision = self.buffer.revision();
+        let brackets = match &mut self.code_brackets {
+            Some((collected_at, brackets)) if *collected_at == revision => brackets,
+            code_brackets => &code_brackets.insert((revision, self.buffer.code_brackets())).1,
+        };
+        brackets::bracket_pair(brackets, self.text_location)
+    }
+
+    fn highlights_on_line(&self, line_idx: LineIdx) -> Vec<(AnnotationType, Range<GraphemeIdx>)> {
+        let mut highlights = Vec::new();
+        if let Some(found) = self
+            .project_matches
+            .as_ref()
+            .and_then(|project_matches| project_matches.get(line_idx))
+        {
+            for range in &found.matches {
+                highlights.push((AnnotationType::Match, range.clone()));
+            }
+        }
+        if let Some(selection) = self.selection_on_line(line_idx) {
+            highlights.push((AnnotationType::Selection, selection));
+        }
+        if let Some((bracket, matching)) = self.bracket_pair {
+            for location in [bracket, matching] {
+                if location.line_idx == line_idx {
+                    highlights.push((
+                        AnnotationType::MatchingBracket,
+                        location.grapheme_idx..location.grapheme_idx.saturating_add(1),
+                    ));
+                }
+            }
+        }
+        highlights
+    }
+
+    fn selection_on_line(&self, line_idx: LineIdx) -> Option<Range<GraphemeIdx>> {
+        let (start, end) = self.selection()?;
+        if line_idx < start.line_idx || line_idx > end.line_idx {
+            return None;
+        }
+        let from = if line_idx == start.line_idx {
+            start.grapheme_idx
+        } else {
+            0
+        };
+        let to = if line_idx == end.line_idx {
+            end.grapheme_idx
+        } else {
+            self.buffer
+                .lines
+                .get(line_idx)
+                .map_or(0, Line::grapheme_count)
+        };
+        Some(from..to)
+    }
+
+    /// Places the caret at the text shown at `position`.
+    pub fn click(&mut self, position: Position) {
+        self.clear_selection();
+        self.text_location = self.position_to_text_location(position);
+        self.scroll_text_location_into_view();
+    }
+
+    /// Extends the selection from where the drag started to the text shown at `position`.
+    pub fn drag_to(&mut self, position: Position) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.text_location);
+        }
+        self.text_location = self.position_to_text_location(position);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// Selects the word shown at `position`.
+    pub fn select_word_at(&mut self, position: Position) {
+        let location = self.position_to_text_location(position);
+        let Some(line) = self.buffer.lines.get(location.line_idx) else {
+            return;
+        };
+        let word = line.word_range_at(location.grapheme_idx);
+        self.selection_anchor = Some(Location {
+            grapheme_idx: word.start,
+            line_idx: location.line_idx,
+        });
+        self.text_location = Location {
+            grapheme_idx: word.end,
+            line_idx: location.line_idx,
+        };
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// Scrolls the viewport up by `lines` without moving the caret, unless it would leave the viewport.
+    pub fn scroll_viewport_up(&mut self, lines: usize) {
+        self.scroll_offset.row = self.scroll_offset.row.saturating_sub(lines);
+        self.keep_text_location_in_viewport();
+        self.set_needs_redraw(true);
+    }
+
+    /// Scrolls the viewport down by `lines` without moving the caret, unless it would leave the viewport.
+    pub fn scroll_viewport_down(&mut self, lines: usize) {
+        self.scroll_offset.row = min(
+            self.scroll_offset.row.saturating_add(lines),
+            self.buffer.height().saturating_sub(1),
+        );
+        self.keep_text_location_in_viewport();
+        self.set_needs_redraw(true);
+    }
+
+    fn keep_text_location_in_viewport(&mut self) {
+        let top = self.scroll_offset.row;
+        let bottom = top.saturating_add(self.size.height.saturating_sub(1));
+        self.text_location.line_idx = self.text_location.line_idx.clamp(top, bottom);
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+    }
+
     pub fn text_location_to_position(&self) -> Position {
         let row = self.text_location.line_idx;
         debug_assert!(row.saturating_sub(1) <= self.buffer.lines.len());
@@ -242,10 +775,21 @@ impl View {
             .buffer
             .lines
             .get(row)
-            .map_or(0, |line| line.width_until(self.text_location.grapheme_idx));
+            .map_or(0, |line| {
+                line.width_until(self.text_location.grapheme_idx, self.settings.tab_width)
+            });
         Position { col, row }
     }
 
+    /// Moves the caret to `target` and centers it, or describes why `target` lies outside of the buffer.
+    pub fn goto(&mut self, target: GotoTarget) -> Result<(), String> {
+        let location = self.buffer.locate(target, self.text_location)?;
+        self.clear_selection();
+        self.text_location = location;
+        self.center_text_location();
+        Ok(())
+    }
+
     pub fn move_up(&mut self, step: usize) {
         self.text_location.line_idx = self.text_location.line_idx.saturating_sub(step);
         self.snap_to_valid_grapheme();
@@ -282,6 +826,87 @@ impl View {
         }
     }
 
+    /// Returns where word-wise movement to the right from the caret ends,
+    /// continuing at the start of the next line once the end of the line is reached.
+    fn next_word_location(&self) -> Location {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let rules = self.settings.word_rules();
+        self.buffer
+            .lines
+            .get(line_idx)
+            .and_then(|line| line.next_word_end(grapheme_idx, rules))
+            .map_or(
+                Location {
+                    grapheme_idx: 0,
+                    line_idx: min(line_idx.saturating_add(1), self.buffer.height()),
+                },
+                |grapheme_idx| Location {
+                    grapheme_idx,
+                    line_idx,
+                },
+            )
+    }
+
+    /// Returns where word-wise movement to the left from the caret ends,
+    /// continuing at the end of the previous line once the start of the line is reached.
+    fn previous_word_location(&self) -> Location {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let rules = self.settings.word_rules();
+        if let Some(grapheme_idx) = self
+            .buffer
+            .lines
+            .get(line_idx)
+            .and_then(|line| line.previous_word_start(grapheme_idx, rules))
+        {
+            return Location {
+                grapheme_idx,
+                line_idx,
+            };
+        }
+        if line_idx == 0 {
+            return self.text_location;
+        }
+        let line_idx = line_idx.saturating_sub(1);
+        Location {
+            grapheme_idx: self
+                .buffer
+                .lines
+                .get(line_idx)
+                .map_or(0, Line::grapheme_count),
+            line_idx,
+        }
+    }
+
+    pub fn move_word_right(&mut self) {
+        self.text_location = self.next_word_location();
+    }
+
+    pub fn move_word_left(&mut self) {
+        self.text_location = self.previous_word_location();
+    }
+
+    pub fn delete_word(&mut self) {
+        self.clear_selection();
+        let end = self.next_word_location();
+        self.buffer.delete_range(self.text_location, end);
+        self.set_needs_redraw(true);
+    }
+
+    pub fn delete_word_backward(&mut self) {
+        self.clear_selection();
+        let start = self.previous_word_location();
+        self.buffer.delete_range(start, self.text_location);
+        self.text_location = start;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
     pub fn move_to_start_of_line(&mut self) {
         self.text_location.grapheme_idx = 0;
     }
@@ -323,6 +948,7 @@ impl UIComponent for View {
     }
     fn set_size(&mut self, size: Size) {
         self.size = size;
+        self.drawn_scroll_offset = None;
         self.scroll_text_location_into_view();
     }
 
@@ -331,6 +957,22 @@ impl UIComponent for View {
         let end_y = origin_row.saturating_add(height);
         let top_third = height.div_ceil(3);
         let scroll_top = self.scroll_offset.row;
+
+        // If we only scrolled by a few lines, let the terminal move the rows which are still visible.
+        if let Some(drawn) = self.drawn_scroll_offset {
+            if drawn.col == self.scroll_offset.col {
+                let delta = scroll_top.abs_diff(drawn.row);
+                if delta > 0 && delta.saturating_mul(2) < height {
+                    if scroll_top > drawn.row {
+                        Terminal::scroll_up(origin_row..end_y, delta)?;
+                    } else {
+                        Terminal::scroll_down(origin_row..end_y, delta)?;
+                    }
+                }
+            }
+        }
+        self.drawn_scroll_offset = Some(self.scroll_offset);
+
         for current_row in origin_row..end_y {
             let line_idx = current_row
                 .saturating_sub(origin_row)
@@ -346,12 +988,21 @@ impl UIComponent for View {
                     .then_some(self.text_location.grapheme_idx);
                 Terminal::print_annotated_row(
                     current_row,
-                    &line.get_annotated_visible_substr(left..right, query, selected_match),
+                    &line.get_annotated_visible_substr(
+                        left..right,
+                        query,
+                        selected_match,
+                        &self.highlights_on_line(line_idx),
+                        self.settings.tab_width,
+                        self.settings.show_whitespace,
+                    ),
                 )?;
-            } else if current_row == top_third && self.buffer.is_empty() {
+            } else if current_row == top_third && self.buffer.is_empty() && !self.is_scratch() {
                 Self::render_line(current_row, &Self::build_welcome_message(width))?;
             } else {
-                Self::render_line(current_row, "~")?;
+                let mut marker = AnnotatedString::from("~");
+                marker.add_annotation(AnnotationType::Gutter, 0, 1);
+                Terminal::print_annotated_row(current_row, &marker)?;
             }
         }
         Ok(())
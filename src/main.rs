@@ -7,8 +7,36 @@
     clippy::integer_division
 )]
 mod editor;
-use editor::Editor;
+use editor::{run_batch, Arguments, Editor, Invocation, USAGE};
+use std::{env, process::ExitCode};
 
-fn main() {
-    Editor::new().unwrap().run();
+// clippy::print_stdout: Help and version are meant for stdout, and are printed before the editor takes over the terminal
+#[allow(clippy::print_stdout)]
+fn main() -> ExitCode {
+    match Arguments::parse(env::args().skip(1)) {
+        Ok(Invocation::Edit(arguments)) => {
+            Editor::new(&arguments).unwrap().run();
+            ExitCode::SUCCESS
+        }
+        Ok(Invocation::Batch(arguments)) => match run_batch(&arguments) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("stack: {err}");
+                ExitCode::FAILURE
+            }
+        },
+        Ok(Invocation::Help) => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Ok(Invocation::Version) => {
+            println!("{}", Invocation::version());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("stack: {err}");
+            eprintln!("Try `stack --help` for more information.");
+            ExitCode::FAILURE
+        }
+    }
 }